@@ -1,37 +1,55 @@
-use std::{collections::BTreeMap, fs::File, sync::Arc};
+use std::{collections::{BTreeMap, HashMap}, fs::File, sync::Arc};
 use futures::lock::Mutex;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use serde::de::DeserializeOwned;
 use super::*;
 
-pub type StationData = BTreeMap<DateTime<Utc>, WxEntry>;
-pub type StationDatabase = Arc<Mutex<StationDatabaseInternal>>;
+// the database can hold any serializable series, but weather entries are the default
+pub type StationData<T = WxEntry> = BTreeMap<DateTime<Utc>, T>;
+pub type StationDatabase<T = WxEntry> = Arc<Mutex<StationDatabaseInternal<T>>>;
 #[derive(Debug, Clone)]
-pub struct StationDatabaseInternal {
+pub struct StationDatabaseInternal<T = WxEntry> {
     pub station: Station,
-    pub data: StationData
+    pub data: StationData<T>
 }
 
 pub fn new_station_db(station: Station) -> StationDatabase {
+    new_station_db_of(station)
+}
+
+// for databases of something other than weather entries, e.g.
+// new_station_db_of::<ClimateNormals>(station)
+pub fn new_station_db_of<T>(station: Station) -> StationDatabase<T> {
     return Arc::new(Mutex::from(StationDatabaseInternal {
         station: station,
         data: BTreeMap::new()
     }))
 }
 
-pub trait DatabaseFuncs { // not sure what to call this
+pub trait DatabaseFuncs<T> { // not sure what to call this
     #[allow(async_fn_in_trait)]
-    async fn add(&self, child: StationData, replace: bool);
+    async fn add(&self, child: StationData<T>, replace: bool);
     #[allow(async_fn_in_trait)]
     async fn export(&self, name: &str, date: DateTime<Utc>) -> Result<()>;
     #[allow(async_fn_in_trait)]
     async fn trim(&self);
     #[allow(async_fn_in_trait)]
-    async fn full_update(&self, child: Result<StationData>, replace: bool, name: &str, date: DateTime<Utc>) -> Result<()>;
+    async fn range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> StationData<T>;
+    #[allow(async_fn_in_trait)]
+    async fn group_by_local_day<Tz: TimeZone>(&self, tz: &Tz) -> BTreeMap<NaiveDate, Vec<T>>;
+    #[cfg(feature = "msgpack")]
+    #[allow(async_fn_in_trait)]
+    async fn export_msgpack(&self, path: &str) -> Result<()>;
+    #[cfg(feature = "msgpack")]
+    #[allow(async_fn_in_trait)]
+    async fn load_msgpack(&self, path: &str, replace: bool) -> Result<()>;
+    #[allow(async_fn_in_trait)]
+    async fn full_update(&self, child: Result<StationData<T>>, replace: bool, name: &str, date: DateTime<Utc>) -> Result<()>;
 }
 
 
-impl DatabaseFuncs for StationDatabase {
-    async fn add(&self, child: StationData, replace: bool) {
+impl<T: Serialize + DeserializeOwned + Clone> DatabaseFuncs<T> for StationDatabase<T> {
+    async fn add(&self, child: StationData<T>, replace: bool) {
         let mut db = self.lock().await;
         for (k , v) in child {
             if replace || !db.data.contains_key(&k) {
@@ -42,8 +60,10 @@ impl DatabaseFuncs for StationDatabase {
     
     async fn export(&self, name: &str, date: DateTime<Utc>) -> Result<()> {
         let file_path: String = format!("data/{}_{}.json", name, date.format("%Y-%m-%d"));
-        let mut write_tree: StationData = BTreeMap::new();
+        let mut write_tree: StationData<T> = BTreeMap::new();
         
+        // copy the day out and release the lock before any serialization or
+        // file I/O, so writers are only blocked for the clone
         let db = self.lock().await;
         for (dt, entry) in db.data.iter() {
             if dt.date_naive() == date.date_naive() {
@@ -70,7 +90,42 @@ impl DatabaseFuncs for StationDatabase {
         }
     }
 
-    async fn full_update(&self, child: Result<StationData>, replace: bool, name: &str, date: DateTime<Utc>) -> Result<()> {
+    // entries with start <= time <= end
+    async fn range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> StationData<T> {
+        let db = self.lock().await;
+        db.data.range(start..=end).map(|(k, v)| (*k, v.clone())).collect()
+    }
+
+    // entries bucketed by their calendar day in tz, in time order
+    async fn group_by_local_day<Tz: TimeZone>(&self, tz: &Tz) -> BTreeMap<NaiveDate, Vec<T>> {
+        let db = self.lock().await;
+        let mut days: BTreeMap<NaiveDate, Vec<T>> = BTreeMap::new();
+        for (dt, entry) in db.data.iter() {
+            days.entry(dt.with_timezone(tz).date_naive()).or_default().push(entry.clone());
+        }
+        days
+    }
+
+    // the whole database in MessagePack, which loads much faster than JSON
+    #[cfg(feature = "msgpack")]
+    async fn export_msgpack(&self, path: &str) -> Result<()> {
+        // serialize a snapshot so the lock is only held for the clone
+        let snapshot = self.lock().await.data.clone();
+        let bytes = rmp_serde::to_vec_named(&snapshot)?;
+
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    async fn load_msgpack(&self, path: &str, replace: bool) -> Result<()> {
+        let bytes = std::fs::read(path)?;
+        let child: StationData<T> = rmp_serde::from_slice(&bytes)?;
+        self.add(child, replace).await;
+        Ok(())
+    }
+
+    async fn full_update(&self, child: Result<StationData<T>>, replace: bool, name: &str, date: DateTime<Utc>) -> Result<()> {
         let one_day = Duration::days(1);
 
         self.add(child.unwrap_or_default(), replace).await;
@@ -79,4 +134,527 @@ impl DatabaseFuncs for StationDatabase {
         self.trim().await;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// entry closest in time to dt, on either side
+fn nearest<T>(data: &StationData<T>, dt: DateTime<Utc>) -> Option<(&DateTime<Utc>, &T)> {
+    let before = data.range(..=dt).next_back();
+    let after = data.range(dt..).next();
+
+    match (before, after) {
+        (Some(b), Some(a)) => if dt - *b.0 <= *a.0 - dt { Some(b) } else { Some(a) },
+        (b, a) => b.or(a),
+    }
+}
+
+// functions that only make sense for weather entries
+pub trait WxDatabaseFuncs {
+    #[allow(async_fn_in_trait)]
+    async fn pressure_tendency(&self, end: DateTime<Utc>, window: Duration) -> Option<f32>;
+    #[allow(async_fn_in_trait)]
+    async fn diurnal_range<Tz: TimeZone>(&self, date: NaiveDate, tz: &Tz) -> Option<f32>;
+    #[allow(async_fn_in_trait)]
+    async fn precip_transitions(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(DateTime<Utc>, PrecipTransition)>;
+    #[allow(async_fn_in_trait)]
+    async fn resample_minmax(&self, interval: Duration) -> BTreeMap<DateTime<Utc>, (WxEntry, WxEntry)>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecipTransition {
+    Started,
+    Stopped,
+    IntensityChanged,
+}
+
+impl WxDatabaseFuncs for StationDatabase<WxEntry> {
+    // signed change in pressure (end minus start, hPa) between the entries
+    // nearest to end - window and end. uses sea level pressure when both ends
+    // have it, station pressure otherwise. None if either entry is more than
+    // half the window away from its target time, e.g. across a data gap.
+    async fn pressure_tendency(&self, end: DateTime<Utc>, window: Duration) -> Option<f32> {
+        let db = self.lock().await;
+        let tolerance = window / 2;
+        let start_target = end - window;
+        let (start_dt, start) = nearest(&db.data, start_target)?;
+        let (end_dt, end_entry) = nearest(&db.data, end)?;
+
+        if start_dt == end_dt
+            || (*start_dt - start_target).abs() > tolerance
+            || (*end_dt - end).abs() > tolerance {
+            return None;
+        }
+
+        match (start.best_slp(), end_entry.best_slp()) {
+            (Some(s), Some(e)) => Some(e - s),
+            _ => Some(end_entry.best_pressure()? - start.best_pressure()?),
+        }
+    }
+
+    // high minus low temperature (°F) over the given local day
+    async fn diurnal_range<Tz: TimeZone>(&self, date: NaiveDate, tz: &Tz) -> Option<f32> {
+        let db = self.lock().await;
+        let temps: Vec<f32> = db.data.iter()
+            .filter(|(dt, _)| dt.with_timezone(tz).date_naive() == date)
+            .filter_map(|(_, e)| e.best_temperature())
+            .collect();
+
+        let high = temps.iter().cloned().reduce(f32::max)?;
+        let low = temps.iter().cloned().reduce(f32::min)?;
+        Some(high - low)
+    }
+
+    // changes in the decoded present weather between consecutive entries.
+    // entries without weather codes are skipped.
+    async fn precip_transitions(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(DateTime<Utc>, PrecipTransition)> {
+        let db = self.lock().await;
+        let mut transitions = vec![];
+        let mut previous: Option<Intensity> = None;
+
+        for (dt, entry) in db.data.range(start..=end) {
+            let current = match entry.wx.or_else(|| entry.wx_from_codes()) {
+                Some(wx) => wx.precip_intensity(),
+                None => continue,
+            };
+
+            if let Some(prev) = previous {
+                let transition = match (prev.is_none(), current.is_none()) {
+                    (true, false) => Some(PrecipTransition::Started),
+                    (false, true) => Some(PrecipTransition::Stopped),
+                    (false, false) if prev != current => Some(PrecipTransition::IntensityChanged),
+                    _ => None,
+                };
+
+                if let Some(t) = transition {
+                    transitions.push((*dt, t));
+                }
+            }
+
+            previous = Some(current);
+        }
+
+        transitions
+    }
+
+    // buckets of length interval (aligned to the unix epoch), each holding an
+    // entry of per-field minimums and one of per-field maximums over the
+    // entries in that bucket. precip amounts are taken per field (rain, snow,
+    // unknown). non-numeric fields (wind direction, sky cover, weather codes)
+    // and the cloud base height, which only describes a single observation's
+    // cloud base temperature, are left empty.
+    async fn resample_minmax(&self, interval: Duration) -> BTreeMap<DateTime<Utc>, (WxEntry, WxEntry)> {
+        let db = self.lock().await;
+        let mut buckets = BTreeMap::new();
+
+        let secs = interval.num_seconds();
+        if secs <= 0 {
+            return buckets;
+        }
+
+        for (dt, entry) in db.data.iter() {
+            let key = dt.timestamp().div_euclid(secs) * secs;
+            let Some(bucket_start) = DateTime::from_timestamp(key, 0) else {
+                continue;
+            };
+
+            let (min, max) = buckets.entry(bucket_start).or_insert_with(|| {
+                let mut empty = WxEntry::empty(&db.station);
+                empty.date_time = bucket_start;
+                (empty.clone(), empty)
+            });
+
+            extend_entry(min, entry, f32::min);
+            extend_entry(max, entry, f32::max);
+        }
+
+        buckets
+    }
+}
+
+fn extend_value(acc: &mut Option<f32>, value: Option<f32>, pick: fn(f32, f32) -> f32) {
+    *acc = match (*acc, value) {
+        (Some(a), Some(v)) => Some(pick(a, v)),
+        (a, v) => a.or(v),
+    };
+}
+
+fn extend_precip(acc: &mut Option<Precip>, value: Option<Precip>, pick: fn(f32, f32) -> f32) {
+    *acc = match (*acc, value) {
+        (Some(a), Some(v)) => Some(Precip {
+            unknown: pick(a.unknown, v.unknown),
+            rain: pick(a.rain, v.rain),
+            snow: pick(a.snow, v.snow),
+        }),
+        (a, v) => a.or(v),
+    };
+}
+
+fn extend_entry(acc: &mut WxEntry, entry: &WxEntry, pick: fn(f32, f32) -> f32) {
+    extend_value(&mut acc.cape, entry.cape, pick);
+    extend_value(&mut acc.max_temp_today, entry.max_temp_today, pick);
+    extend_value(&mut acc.min_temp_today, entry.min_temp_today, pick);
+    extend_value(&mut acc.cloud_base_temp, entry.cloud_base_temp, pick);
+    extend_precip(&mut acc.precip, entry.precip, pick);
+    extend_precip(&mut acc.precip_1h, entry.precip_1h, pick);
+    extend_precip(&mut acc.precip_6h, entry.precip_6h, pick);
+    extend_precip(&mut acc.precip_24h, entry.precip_24h, pick);
+    extend_value(&mut acc.altimeter, entry.altimeter, pick);
+    extend_value(&mut acc.best_slp, entry.best_slp, pick);
+    extend_value(&mut acc.precip_probability, entry.precip_probability, pick);
+
+    for (layer, l) in entry.layers.iter() {
+        let a = acc.layers.entry(*layer).or_insert_with(|| WxEntryLayer::empty(*layer));
+        a.height_msl = a.height_msl.or(l.height_msl);
+
+        extend_value(&mut a.temperature, l.temperature, pick);
+        extend_value(&mut a.dewpoint, l.dewpoint, pick);
+        extend_value(&mut a.pressure, l.pressure, pick);
+        extend_value(&mut a.wind_speed, l.wind_speed, pick);
+        extend_value(&mut a.wind_gust, l.wind_gust, pick);
+        extend_value(&mut a.visibility, l.visibility, pick);
+        extend_value(&mut a.relative_humidity, l.relative_humidity, pick);
+        extend_value(&mut a.slp, l.slp, pick);
+        extend_value(&mut a.wind_chill, l.wind_chill, pick);
+        extend_value(&mut a.heat_index, l.heat_index, pick);
+        extend_value(&mut a.apparent_temp, l.apparent_temp, pick);
+        extend_value(&mut a.theta_e, l.theta_e, pick);
+    }
+}
+
+
+// one StationDatabase per station, keyed by station id. each station keeps its
+// own lock, so the outer lock is only held to look a station up.
+pub struct MultiStationDatabase<T = WxEntry> {
+    stations: Mutex<HashMap<String, StationDatabase<T>>>,
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> MultiStationDatabase<T> {
+    pub fn new() -> Self {
+        MultiStationDatabase { stations: Mutex::new(HashMap::new()) }
+    }
+
+    // returns the existing database if the id is already registered
+    pub async fn add_station(&self, id: &str, station: Station) -> StationDatabase<T> {
+        self.stations.lock().await
+            .entry(id.to_string())
+            .or_insert_with(|| new_station_db_of(station))
+            .clone()
+    }
+
+    pub async fn station(&self, id: &str) -> Option<StationDatabase<T>> {
+        self.stations.lock().await.get(id).cloned()
+    }
+
+    pub async fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.stations.lock().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    async fn get(&self, id: &str) -> Result<StationDatabase<T>> {
+        self.station(id).await.ok_or_else(|| anyhow::anyhow!("Unknown station {id:?}"))
+    }
+
+    pub async fn add(&self, id: &str, child: StationData<T>, replace: bool) -> Result<()> {
+        self.get(id).await?.add(child, replace).await;
+        Ok(())
+    }
+
+    // written to data/{id}_{date}.json
+    pub async fn export(&self, id: &str, date: DateTime<Utc>) -> Result<()> {
+        self.get(id).await?.export(id, date).await
+    }
+
+    pub async fn range(&self, id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<StationData<T>> {
+        Ok(self.get(id).await?.range(start, end).await)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Default for MultiStationDatabase<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::climate_normals::ClimateNormals;
+    use futures::executor::block_on;
+    use chrono_tz::US::Eastern;
+    use crate::test_util::{entry_at, test_station};
+
+    #[test]
+    fn test_pressure_tendency() {
+        let station = test_station();
+        let db: StationDatabase = new_station_db(station.clone());
+        let end = Utc::now();
+
+        let mut child = BTreeMap::new();
+        for i in 0..=6 {
+            let mut entry = WxEntry::empty(&station);
+            let mut sea_level = WxEntryLayer::empty(Layer::SeaLevel);
+            sea_level.pressure = Some(1012. - i as f32);
+            entry.layers.insert(Layer::SeaLevel, sea_level);
+            entry.date_time = end - Duration::hours(6 - i);
+            child.insert(entry.date_time, entry);
+        }
+        block_on(db.add(child, false));
+
+        let tendency = block_on(db.pressure_tendency(end, Duration::hours(3))).unwrap();
+        assert!((tendency + 3.).abs() < 1e-4, "{tendency}");
+
+        // nearest entries are used when the window doesn't line up exactly
+        let tendency = block_on(db.pressure_tendency(end + Duration::minutes(10), Duration::minutes(170))).unwrap();
+        assert!((tendency + 3.).abs() < 1e-4, "{tendency}");
+
+        // a gap: the last entry is 4 days before the requested end
+        let gap = block_on(db.pressure_tendency(end + Duration::days(4), Duration::hours(3)));
+        assert_eq!(gap, None);
+        // and the start of the window falls in the gap
+        let mut late = BTreeMap::new();
+        late.insert(end + Duration::days(4), block_on(db.range(end, end))[&end].clone());
+        block_on(db.add(late, false));
+        assert_eq!(block_on(db.pressure_tendency(end + Duration::days(4), Duration::hours(3))), None);
+
+        let empty = new_station_db(station.clone());
+        assert_eq!(block_on(empty.pressure_tendency(end, Duration::hours(3))), None);
+
+        // no pressure at the ends
+        let mut child = BTreeMap::new();
+        child.insert(end, WxEntry::empty(&station));
+        child.insert(end - Duration::hours(3), WxEntry::empty(&station));
+        let no_pressure: StationDatabase = new_station_db(station);
+        block_on(no_pressure.add(child, false));
+        assert_eq!(block_on(no_pressure.pressure_tendency(end, Duration::hours(3))), None);
+    }
+
+    #[test]
+    fn test_diurnal_range() {
+        let station = test_station();
+        let db: StationDatabase = new_station_db(station.clone());
+        let date = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
+        let midnight = Eastern.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+
+        // 60°F at midnight, peaking at 80°F at 15:00, then outside the day
+        let mut child = BTreeMap::new();
+        for (hour, temp) in [(0, 60.), (6, 58.), (15, 80.), (23, 65.), (25, 20.)] {
+            let dt = midnight + Duration::hours(hour);
+            child.insert(dt, entry_at(&station, dt, temp));
+        }
+        block_on(db.add(child, false));
+
+        assert_eq!(block_on(db.diurnal_range(date, &Eastern)), Some(22.));
+        assert_eq!(block_on(db.diurnal_range(date.pred_opt().unwrap(), &Eastern)), None);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let station = test_station();
+        let db: StationDatabase = new_station_db(station.clone());
+        let start = Utc::now();
+
+        let mut child = BTreeMap::new();
+        for i in 0..24 {
+            let mut entry = entry_at(&station, start + Duration::hours(i), 60. + i as f32);
+            let surface = entry.layers.get_mut(&Layer::NearSurface).unwrap();
+            surface.dewpoint = Some(50.);
+            surface.wind_speed = Some(5.);
+            entry.raw_metar = Some("KPSM 151853Z 23012KT 10SM FEW050 30/20 A2992".into());
+            child.insert(entry.date_time, entry);
+        }
+        block_on(db.add(child, false));
+
+        let path = std::env::temp_dir().join(format!("wxer_lib_test_{}.msgpack", std::process::id()));
+        let path = path.to_str().unwrap();
+        block_on(db.export_msgpack(path)).unwrap();
+
+        let loaded: StationDatabase = new_station_db(station);
+        block_on(loaded.load_msgpack(path, false)).unwrap();
+        let binary_len = std::fs::metadata(path).unwrap().len() as usize;
+        std::fs::remove_file(path).unwrap();
+
+        let original = serde_json::to_string(&block_on(db.lock()).data).unwrap();
+        let reloaded = serde_json::to_string(&block_on(loaded.lock()).data).unwrap();
+        assert_eq!(original, reloaded);
+        assert!(binary_len < original.len(), "{binary_len} >= {}", original.len());
+    }
+
+    #[test]
+    fn test_group_by_local_day() {
+        let db = new_station_db_of::<f32>(test_station());
+
+        // 03:00 UTC on the 16th is still the 15th in New York
+        let mut child = BTreeMap::new();
+        for (time, value) in [("2024-07-15T18:00:00Z", 1.), ("2024-07-16T03:00:00Z", 2.), ("2024-07-16T05:00:00Z", 3.)] {
+            child.insert(time.parse::<DateTime<Utc>>().unwrap(), value);
+        }
+        block_on(db.add(child, false));
+
+        let days = block_on(db.group_by_local_day(&Eastern));
+        let the_15th = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
+        let the_16th = NaiveDate::from_ymd_opt(2024, 7, 16).unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[&the_15th], vec![1., 2.]);
+        assert_eq!(days[&the_16th], vec![3.]);
+
+        let utc_days = block_on(db.group_by_local_day(&Utc));
+        assert_eq!(utc_days[&the_16th], vec![2., 3.]);
+    }
+
+    #[test]
+    fn test_precip_transitions() {
+        let station = test_station();
+        let db: StationDatabase = new_station_db(station.clone());
+        let start = "2024-07-15T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let codes: [Option<&[&str]>; 7] = [
+            Some(&[]),
+            Some(&["VCSH"]),
+            Some(&["-RA"]),
+            None, // missing, skipped
+            Some(&["+RA", "BR"]),
+            Some(&["BR"]),
+            Some(&[]),
+        ];
+
+        let mut child = BTreeMap::new();
+        for (i, c) in codes.iter().enumerate() {
+            let mut entry = WxEntry::empty(&station);
+            entry.date_time = start + Duration::hours(i as i64);
+            entry.wx_codes = c.map(|x| x.iter().map(|s| s.to_string()).collect());
+            entry.fill_in_calculated_values();
+            child.insert(entry.date_time, entry);
+        }
+        block_on(db.add(child, false));
+
+        let transitions = block_on(db.precip_transitions(start, start + Duration::hours(6)));
+        assert_eq!(transitions, vec![
+            (start + Duration::hours(2), PrecipTransition::Started),
+            (start + Duration::hours(4), PrecipTransition::IntensityChanged),
+            (start + Duration::hours(5), PrecipTransition::Stopped),
+        ]);
+
+        let later = block_on(db.precip_transitions(start + Duration::hours(3), start + Duration::hours(6)));
+        assert_eq!(later, vec![(start + Duration::hours(5), PrecipTransition::Stopped)]);
+    }
+
+    #[test]
+    fn test_resample_minmax() {
+        let station = test_station();
+        let db: StationDatabase = new_station_db(station.clone());
+        let start = "2024-07-15T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        // every 10 minutes for two hours, with a gust spike at 12:40
+        let mut child = BTreeMap::new();
+        for i in 0..12 {
+            let mut entry = entry_at(&station, start + Duration::minutes(10 * i), 70. + i as f32);
+            let surface = entry.layers.get_mut(&Layer::NearSurface).unwrap();
+            surface.wind_speed = Some(10.);
+            surface.wind_gust = Some(if i == 4 { 45. } else { 15. });
+            // a burst of rain at 12:20
+            let rain = if i == 2 { 0.4 } else { 0.05 };
+            entry.precip = Some(Precip { unknown: 0., rain, snow: 0. });
+            entry.precip_1h = Some(Precip { unknown: 0., rain: rain * 2., snow: 0.1 });
+            entry.precip_6h = Some(Precip { unknown: 0., rain: 1. + i as f32 * 0.1, snow: 0. });
+            entry.precip_24h = Some(Precip { unknown: i as f32, rain: 2., snow: 0. });
+            entry.max_temp_today = Some(80. + i as f32);
+            entry.min_temp_today = Some(60. - i as f32);
+            entry.cloud_base_temp = Some(50. - i as f32);
+            entry.cloud_base_height = Some(5000);
+            child.insert(entry.date_time, entry);
+        }
+        block_on(db.add(child, false));
+
+        let buckets = block_on(db.resample_minmax(Duration::hours(1)));
+        assert_eq!(buckets.len(), 2);
+
+        let (min, max) = &buckets[&start];
+        let (min_sfc, max_sfc) = (&min.layers[&Layer::NearSurface], &max.layers[&Layer::NearSurface]);
+        assert_eq!(min.date_time, start);
+        assert_eq!(min_sfc.wind_gust, Some(15.));
+        assert_eq!(max_sfc.wind_gust, Some(45.));
+        assert_eq!(min_sfc.temperature, Some(70.));
+        assert_eq!(max_sfc.temperature, Some(75.));
+        assert!(max_sfc.wind_direction.is_none());
+
+        assert_eq!(min.precip.map(|p| p.rain), Some(0.05));
+        assert_eq!(max.precip.map(|p| p.rain), Some(0.4));
+        assert_eq!(max.precip_1h.map(|p| (p.rain, p.snow)), Some((0.8, 0.1)));
+        assert_eq!(min.precip_6h.map(|p| p.rain), Some(1.));
+        assert_eq!(max.precip_6h.map(|p| p.rain), Some(1.5));
+        assert_eq!(min.precip_24h.map(|p| p.unknown), Some(0.));
+        assert_eq!(max.precip_24h.map(|p| p.unknown), Some(5.));
+        assert_eq!((min.max_temp_today, max.max_temp_today), (Some(80.), Some(85.)));
+        assert_eq!((min.min_temp_today, max.min_temp_today), (Some(55.), Some(60.)));
+        assert_eq!((min.cloud_base_temp, max.cloud_base_temp), (Some(45.), Some(50.)));
+        assert_eq!(max.cloud_base_height, None);
+
+        let (_, max) = &buckets[&(start + Duration::hours(1))];
+        assert_eq!(max.layers[&Layer::NearSurface].wind_gust, Some(15.));
+        assert_eq!(max.layers[&Layer::NearSurface].temperature, Some(81.));
+
+        assert!(block_on(db.resample_minmax(Duration::zero())).is_empty());
+    }
+
+    #[test]
+    fn test_multi_station_database() {
+        let multi: MultiStationDatabase = MultiStationDatabase::new();
+        let now = Utc::now();
+
+        let mut other = test_station();
+        other.name = "Other".into();
+        block_on(multi.add_station("PSM", test_station()));
+        block_on(multi.add_station("DAW", other));
+        assert_eq!(block_on(multi.ids()), vec!["DAW", "PSM"]);
+
+        let entry = |station: &Station, temp: f32, dt: DateTime<Utc>| {
+            BTreeMap::from([(dt, entry_at(station, dt, temp))])
+        };
+
+        block_on(multi.add("PSM", entry(&test_station(), 70., now), false)).unwrap();
+        block_on(multi.add("PSM", entry(&test_station(), 72., now - Duration::hours(1)), false)).unwrap();
+        block_on(multi.add("DAW", entry(&test_station(), 50., now), false)).unwrap();
+
+        let psm = block_on(multi.range("PSM", now - Duration::hours(2), now)).unwrap();
+        let daw = block_on(multi.range("DAW", now - Duration::hours(2), now)).unwrap();
+        assert_eq!(psm.len(), 2);
+        assert_eq!(daw.len(), 1);
+        assert_eq!(daw[&now].best_temperature(), Some(50.));
+        assert_eq!(psm[&now].best_temperature(), Some(70.));
+
+        // registering again keeps the existing data
+        let db = block_on(multi.add_station("DAW", test_station()));
+        assert_eq!(block_on(db.lock()).station.name, "Other");
+        assert_eq!(block_on(db.lock()).data.len(), 1);
+
+        assert!(block_on(multi.add("XXX", BTreeMap::new(), false)).is_err());
+        assert!(block_on(multi.range("XXX", now, now)).is_err());
+    }
+
+    #[test]
+    fn test_generic_database() {
+        let db = new_station_db_of::<ClimateNormals>(test_station());
+        let now = Utc::now();
+
+        let mut child = BTreeMap::new();
+        child.insert(now - Duration::days(3), ClimateNormals { min_temp: 10., avg_temp: 20., max_temp: 30. });
+        child.insert(now - Duration::hours(2), ClimateNormals { min_temp: 11., avg_temp: 21., max_temp: 31. });
+        child.insert(now, ClimateNormals { min_temp: 12., avg_temp: 22., max_temp: 32. });
+
+        block_on(db.add(child, false));
+        assert_eq!(block_on(db.range(now - Duration::hours(3), now)).len(), 2);
+
+        // existing entries are kept unless replacing
+        let mut update = BTreeMap::new();
+        update.insert(now, ClimateNormals { min_temp: 0., avg_temp: 0., max_temp: 0. });
+        block_on(db.add(update.clone(), false));
+        assert_eq!(block_on(db.range(now, now))[&now].max_temp, 32.);
+        block_on(db.add(update, true));
+        assert_eq!(block_on(db.range(now, now))[&now].max_temp, 0.);
+
+        block_on(db.trim());
+        assert_eq!(block_on(db.lock()).data.len(), 2);
+    }
+}
@@ -13,3 +13,28 @@ pub mod asos;
 pub mod rpi_station;
 pub mod climate_normals;
 pub mod open_meteo;
+
+// the record's original line in source, byte for byte (quoting and whitespace
+// included), without the line ending. a record with quoted newlines only gets
+// its first line.
+pub(crate) fn raw_csv_line(source: &str, record: &csv::StringRecord) -> Option<String> {
+    let start = record.position()?.byte() as usize;
+    // after a \r\n the position can point at the \n
+    source.get(start..)?.trim_start_matches(['\r', '\n']).lines().next().map(String::from)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_csv_line() {
+        let source = "a,b,c\n1 ,\"x, y\",3\r\n4,5,6\n";
+        let mut rdr = csv::Reader::from_reader(source.as_bytes());
+        let records: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+
+        assert_eq!(raw_csv_line(source, &records[0]).as_deref(), Some(r#"1 ,"x, y",3"#));
+        assert_eq!(raw_csv_line(source, &records[1]).as_deref(), Some("4,5,6"));
+    }
+}
@@ -4,7 +4,7 @@ use crate::{db::StationData, ignore_none, inhg_to_hpa, CloudLayer, Direction, La
 
 use chrono::{DateTime, Duration, Timelike, Utc};
 use serde::Deserialize;
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 
 pub async fn import(station_name: &str, network: &str, station: Station) -> Result<StationData> {
     let url = format!("http://mesonet.agron.iastate.edu/json/current.py?station={}&network={}", station_name, network);
@@ -16,11 +16,18 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
         .text()
         .await?;
 
-    let raw_ob: RawASOSObservation = serde_json::from_str(&resp)?;
+    parse(&resp, station)
+}
+
+fn parse(resp: &str, station: Station) -> Result<StationData> {
+    let raw_ob: RawASOSObservation = serde_json::from_str(resp)?;
 
     let mut dt = raw_ob.last_ob.utc_valid.parse::<DateTime<Utc>>()?;
     dt -= Duration::seconds(dt.second() as i64); // round to previous minute
 
+    // lowest reported cloud base, in ft
+    let cloud_base = raw_ob.last_ob.skylevel.iter().flatten().min().cloned();
+
     let skycover = Some(skycover_from_vecs(raw_ob.last_ob.skycover, raw_ob.last_ob.skylevel)?);
 
     let wind_direction = match raw_ob.last_ob.winddirectiondeg {
@@ -28,9 +35,12 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
         None => None
     };
 
-    let precip_today = ignore_none(raw_ob.last_ob.precip_today, |x| {
-        Precip{unknown: x, rain: 0., snow: 0.}
-    });
+    let unknown_precip = |x| Precip{unknown: x, rain: 0., snow: 0.};
+
+    let precip_today = ignore_none(raw_ob.last_ob.precip_today, unknown_precip);
+    let precip_1h = ignore_none(raw_ob.last_ob.precip_1h, unknown_precip);
+    let precip_6h = ignore_none(raw_ob.last_ob.precip_6h, unknown_precip);
+    let precip_24h = ignore_none(raw_ob.last_ob.precip_24h, unknown_precip);
 
     let present_wx = raw_ob.last_ob.present_wx;
 
@@ -45,6 +55,7 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
         pressure: None, 
         wind_direction, 
         wind_speed: raw_ob.last_ob.windspeedkt, 
+        wind_gust: raw_ob.last_ob.windgustkt,
         visibility: raw_ob.last_ob.visibilitymile,
 
         relative_humidity: None,
@@ -64,6 +75,7 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
         pressure: raw_ob.last_ob.mslpmb, 
         wind_direction: None, 
         wind_speed: None, 
+        wind_gust: None,
         visibility: None,
 
         relative_humidity: None,
@@ -79,6 +91,12 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
     layers.insert(Layer::NearSurface, near_surface);
     layers.insert(Layer::SeaLevel, sea_level);
 
+    // temperature at the cloud base, only meaningful with a base to go with it
+    let (cloud_base_temp, cloud_base_height) = match (raw_ob.last_ob.cltmpf, cloud_base) {
+        (Some(t), Some(h)) => (Some(t), Some(h)),
+        _ => (None, None),
+    };
+
     let mut entry: WxEntry = WxEntry { 
         date_time: dt,
         station: station.clone(),
@@ -88,8 +106,16 @@ pub async fn import(station_name: &str, network: &str, station: Station) -> Resu
         cape: None,
         skycover,
         raw_metar: raw_ob.last_ob.raw,
+        provider_raw: Some(resp.to_string()),
+        max_temp_today: raw_ob.last_ob.max_dayairtempF,
+        min_temp_today: raw_ob.last_ob.min_dayairtempF,
+        cloud_base_temp,
+        cloud_base_height,
         precip_today,
         precip: None,
+        precip_1h,
+        precip_6h,
+        precip_24h,
         precip_probability: None,
         wx: None,
         wx_codes: present_wx,
@@ -135,6 +161,9 @@ struct ASOSOb {
     #[serde(rename="windspeed[kt]")]
     windspeedkt: Option<f32>,
 
+    #[serde(rename="windgust[kt]")]
+    windgustkt: Option<f32>,
+
     #[serde(rename="winddirection[deg]")]
     winddirectiondeg: Option<f32>,
 
@@ -161,6 +190,15 @@ struct ASOSOb {
     #[serde(rename="precip_today[in]")]
     precip_today: Option<f32>,
 
+    #[serde(rename="precip_1hour[in]")]
+    precip_1h: Option<f32>,
+
+    #[serde(rename="precip_6hour[in]")]
+    precip_6h: Option<f32>,
+
+    #[serde(rename="precip_24hour[in]")]
+    precip_24h: Option<f32>,
+
     #[serde(rename="cltmpf[F]")]
     cltmpf: Option<f32>,
 
@@ -188,6 +226,152 @@ fn skycover_from_vecs(cover: Vec<Option<String>>, level: Vec<Option<u32>>) -> Re
         }
     }
  
-    Ok(SkyCoverage::Cloudy(skyc))
+    Ok(SkyCoverage::from_layers(skyc))
 }
 
+// parse a METAR-style visibility group ("10SM", "1 1/2SM", "3/4SM") into
+// statute miles. "M" (less than) and "P" (more than) prefixes and a trailing
+// "+" are bounds, so the bound itself is returned.
+pub fn parse_visibility(s: &str) -> Result<f32> {
+    let invalid = || anyhow!("Invalid visibility {s:?}");
+
+    let v = s.trim();
+    let v = v.strip_suffix("SM").unwrap_or(v);
+    let v = v.strip_suffix('+').unwrap_or(v);
+    let v = v.strip_prefix(['M', 'P']).unwrap_or(v);
+
+    // plain digits only, so no signs, "NaN" or "inf"
+    let number = |x: &str| -> Result<f32> {
+        if x.is_empty() || !x.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        Ok(x.parse::<u32>().map_err(|_| invalid())? as f32)
+    };
+
+    let fraction = |x: &str| -> Result<f32> {
+        let (n, d) = x.split_once('/').ok_or_else(invalid)?;
+        let d = number(d)?;
+        if d == 0. {
+            bail!("Zero denominator in visibility {s:?}")
+        }
+        Ok(number(n)? / d)
+    };
+
+    // an optional whole number followed by an optional fraction
+    let parts: Vec<&str> = v.split_whitespace().collect();
+    match parts.as_slice() {
+        [x] if x.contains('/') => fraction(x),
+        [x] => number(x),
+        [whole, frac] => Ok(number(whole)? + fraction(frac)?),
+        _ => Err(invalid()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_station;
+
+    const CURRENT_FIXTURE: &str = r#"{
+        "id": "PSM",
+        "network": "NH_ASOS",
+        "last_ob": {
+            "utc_valid": "2024-07-15T18:53:00Z",
+            "airtemp[F]": 86.0,
+            "max_dayairtemp[F]": 89.1,
+            "min_dayairtemp[F]": 66.2,
+            "dewpointtemp[F]": 68.0,
+            "windspeed[kt]": 12.0,
+            "windgust[kt]": 22.0,
+            "winddirection[deg]": 230.0,
+            "altimeter[in]": 29.92,
+            "mslp[mb]": 1012.8,
+            "skycover[code]": ["FEW", "BKN", null, null],
+            "skylevel[ft]": [5000, 25000, null, null],
+            "visibility[mile]": 10.0,
+            "raw": "KPSM 151853Z 23012G22KT 10SM FEW050 BKN250 30/20 A2992",
+            "presentwx": [],
+            "precip_today[in]": 0.0,
+            "cltmpf[F]": 52.0
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_daily_extremes_and_gust() {
+        let db = parse(CURRENT_FIXTURE, test_station()).unwrap();
+        assert_eq!(db.len(), 1);
+        let entry = db.values().next().unwrap();
+
+        assert_eq!(entry.provider_raw.as_deref(), Some(CURRENT_FIXTURE));
+        assert_eq!(entry.max_temp_today, Some(89.1));
+        assert_eq!(entry.min_temp_today, Some(66.2));
+
+        let surface = entry.surface().unwrap();
+        assert_eq!(surface.temperature, Some(86.0));
+        assert_eq!(surface.wind_gust, Some(22.0));
+
+        // 5000 ft cloud base, kept out of the layers
+        assert_eq!(entry.cloud_base_temp, Some(52.0));
+        assert_eq!(entry.cloud_base_height, Some(5000));
+        assert_eq!(entry.layers.len(), 2);
+
+        // period totals are only set when the payload has them
+        assert!(entry.precip_6h.is_none());
+    }
+
+    #[test]
+    fn test_parse_missing_surface_temperature() {
+        let fixture = CURRENT_FIXTURE.replace(r#""airtemp[F]": 86.0,"#, r#""airtemp[F]": null,"#);
+        let db = parse(&fixture, test_station()).unwrap();
+        let entry = db.values().next().unwrap();
+
+        // the cloud base temperature must not stand in for the surface
+        assert_eq!(entry.best_temperature(), None);
+        assert_eq!(entry.cloud_base_temp, Some(52.0));
+    }
+
+    #[test]
+    fn test_parse_pressure_layers() {
+        let db = parse(CURRENT_FIXTURE, test_station()).unwrap();
+        let entry = db.values().next().unwrap();
+
+        // MSLP only goes on the SeaLevel layer, station pressure is not reported
+        assert_eq!(entry.layers[&Layer::SeaLevel].pressure, Some(1012.8));
+        assert_eq!(entry.surface().unwrap().pressure, None);
+        assert_eq!(entry.best_slp(), Some(1012.8));
+        assert_eq!(entry.altimeter, Some(inhg_to_hpa(29.92)));
+    }
+
+    #[test]
+    fn test_parse_period_precip() {
+        let fixture = CURRENT_FIXTURE.replace(
+            r#""precip_today[in]": 0.0,"#,
+            r#""precip_today[in]": 1.42, "precip_1hour[in]": 0.35, "precip_6hour[in]": 1.1, "precip_24hour[in]": 2.05,"#
+        );
+        let db = parse(&fixture, test_station()).unwrap();
+        let entry = db.values().next().unwrap();
+
+        assert_eq!(entry.precip_today.map(|x| x.unknown), Some(1.42));
+        assert_eq!(entry.precip_1h.map(|x| x.unknown), Some(0.35));
+        assert_eq!(entry.precip_6h.map(|x| x.unknown), Some(1.1));
+        assert_eq!(entry.precip_24h.map(|x| x.unknown), Some(2.05));
+    }
+
+    #[test]
+    fn test_parse_visibility() {
+        assert_eq!(parse_visibility("10SM").unwrap(), 10.);
+        assert_eq!(parse_visibility("1 1/2SM").unwrap(), 1.5);
+        assert_eq!(parse_visibility("M1/4SM").unwrap(), 0.25);
+        assert_eq!(parse_visibility("P6SM").unwrap(), 6.);
+        assert_eq!(parse_visibility("10+").unwrap(), 10.);
+        assert_eq!(parse_visibility("3/4").unwrap(), 0.75);
+
+        assert!(parse_visibility("SM").is_err());
+        assert!(parse_visibility("1/0SM").is_err());
+        assert!(parse_visibility("FEW050").is_err());
+        for bad in ["-1SM", "NaN", "inf", "3 4SM", "1/2 1/2", "1/2 3", "+5SM", "1 1/2 1/2SM", "1/-2SM"] {
+            assert!(parse_visibility(bad).is_err(), "{bad}");
+        }
+    }
+}
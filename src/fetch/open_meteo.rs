@@ -12,6 +12,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use anyhow::{anyhow, Result};
 
+use crate::{Layer, Station, WxEntry, WxEntryLayer};
+
 // todo: convert to WxEntry
 
 #[allow(dead_code)]
@@ -57,6 +59,72 @@ pub async fn import_model_data(coords: (f32, f32), data_type: DataType, model: W
 }
 
 
+// the latest value of each data type, as a single entry
+pub async fn import_current(coords: (f32, f32), data_types: &[DataType], model: WeatherModel) -> Result<WxEntry> {
+
+    let current = data_types.iter().map(|x| x.to_str()).collect::<Vec<_>>().join(",");
+
+    let url = format!("https://api.open-meteo.com/v1/forecast?latitude={:.2}&longitude={:.2}&current={}&models={}&temperature_unit=fahrenheit&windspeed_unit=mph&precipitation_unit=inch", coords.0, coords.1, current, model.to_str());
+
+    let resp: String = reqwest::get(url)
+        .await?
+        .text()
+        .await?;
+
+    parse_current(&resp, data_types)
+}
+
+fn parse_current(raw: &str, data_types: &[DataType]) -> Result<WxEntry> {
+    let resp: OpenMeteoResponse = serde_json::from_str(raw)?;
+    let current = resp.current.ok_or(anyhow!("Current block did not exist in open-meteo response."))?;
+
+    let time = current.get("time").ok_or(anyhow!("Time did not exist in open-meteo response."))?;
+    let time_string = match time {
+        Value::String(t) => String::from(t) + ":00Z",
+        _ => return Err(anyhow!("The type of data from open-meteo is wrong")),
+    };
+
+    let station = Station {
+        name: "Open-Meteo".into(),
+        altitude: resp.elevation.unwrap_or(0.),
+        coords: (resp.latitude, resp.longitude),
+    };
+
+    let mut entry = WxEntry::empty(&station);
+    entry.date_time = time_string.parse::<DateTime<Utc>>()?;
+    entry.provider_raw = Some(raw.to_string());
+
+    let mut near_surface = WxEntryLayer::empty(Layer::NearSurface);
+    near_surface.height_agl = Some(2.0);
+    near_surface.height_msl = Some(station.altitude);
+
+    for data_type in data_types {
+        let value = current.get(data_type.to_str())
+            .ok_or(anyhow!("{} did not exist in open-meteo response.", data_type.to_str()))?
+            .as_f64()
+            .map(|x| x as f32);
+
+        match data_type {
+            DataType::Temperature2m => near_surface.temperature = value,
+            DataType::Dewpoint2m => near_surface.dewpoint = value,
+            DataType::ApparentTemperature => near_surface.apparent_temp = value,
+            DataType::Cape => entry.cape = value,
+        }
+    }
+
+    // the fetched apparent temperature is open-meteo's own, keep it over ours
+    let apparent_temp = near_surface.apparent_temp;
+    entry.layers.insert(Layer::NearSurface, near_surface);
+    entry.fill_in_calculated_values();
+
+    if let (Some(t), Some(surface)) = (apparent_temp, entry.layers.get_mut(&Layer::NearSurface)) {
+        surface.apparent_temp = Some(t);
+    }
+
+    Ok(entry)
+}
+
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModelDataEntry {
     pub model: WeatherModel,
@@ -64,7 +132,7 @@ pub struct ModelDataEntry {
     pub data: BTreeMap<DateTime<Utc>, f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DataType {
     Temperature2m,
     Dewpoint2m,
@@ -95,6 +163,7 @@ pub enum WeatherModel {
     BestMatch,
     GFSSeamless,
     EcmwfIFS,
+    Ensemble, // mean of several models, not fetchable
 }
 
 impl WeatherModel {
@@ -103,6 +172,7 @@ impl WeatherModel {
             WeatherModel::BestMatch => {"best_match"},
             WeatherModel::GFSSeamless => {"gfs_seamless"},
             WeatherModel::EcmwfIFS => {"ecmwf_ifs04"},
+            WeatherModel::Ensemble => {"ensemble"},
         }
     }
 }
@@ -114,6 +184,29 @@ pub struct ModelDataCollection {
     pub data: BTreeMap<DateTime<Utc>, f64>,
 }
 
+impl ModelDataCollection {
+    // mean of the collections at every time they all share
+    pub fn ensemble_mean(collections: &[ModelDataCollection]) -> Result<ModelDataCollection> {
+        let first = collections.first().ok_or(anyhow!("No collections to average."))?;
+
+        if let Some(c) = collections.iter().find(|c| c.data_type != first.data_type) {
+            return Err(anyhow!("Cannot average {} with {}.", first.data_type.to_str(), c.data_type.to_str()));
+        }
+
+        let n = collections.len() as f64;
+        let mut data = BTreeMap::new();
+
+        for dt in first.data.keys() {
+            let values: Option<Vec<f64>> = collections.iter().map(|c| c.data.get(dt).cloned()).collect();
+            if let Some(values) = values {
+                data.insert(*dt, values.iter().sum::<f64>() / n);
+            }
+        }
+
+        Ok(ModelDataCollection { model: WeatherModel::Ensemble, data_type: first.data_type.clone(), data })
+    }
+}
+
 
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
@@ -124,7 +217,97 @@ struct OpenMeteoResponse {
     utc_offset_seconds: i32,
     //timezone: String,
     timezone_abbreviation: String,
-    //elevation: f32,
+    elevation: Option<f32>,
     //hourly_units: HashMap<String, String>,
-    hourly: HashMap<String, Vec<Value>>
+    #[serde(default)]
+    hourly: HashMap<String, Vec<Value>>,
+    current: Option<HashMap<String, Value>>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT_FIXTURE: &str = r#"{
+        "latitude": 43.13,
+        "longitude": -70.93,
+        "generationtime_ms": 0.03,
+        "utc_offset_seconds": 0,
+        "timezone": "GMT",
+        "timezone_abbreviation": "GMT",
+        "elevation": 27.0,
+        "current_units": {
+            "time": "iso8601",
+            "interval": "seconds",
+            "temperature_2m": "°F",
+            "dewpoint_2m": "°F",
+            "cape": "J/kg"
+        },
+        "current": {
+            "time": "2024-07-15T18:45",
+            "interval": 900,
+            "temperature_2m": 84.2,
+            "dewpoint_2m": 66.1,
+            "cape": 1250.0
+        }
+    }"#;
+
+    #[test]
+    fn test_ensemble_mean() {
+        let t0 = "2024-07-15T18:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let t1 = "2024-07-15T19:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let t2 = "2024-07-15T20:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let gfs = ModelDataCollection {
+            model: WeatherModel::GFSSeamless,
+            data_type: DataType::Temperature2m,
+            data: BTreeMap::from([(t0, 80.), (t1, 82.), (t2, 84.)]),
+        };
+        let ecmwf = ModelDataCollection {
+            model: WeatherModel::EcmwfIFS,
+            data_type: DataType::Temperature2m,
+            data: BTreeMap::from([(t0, 78.), (t1, 81.)]),
+        };
+
+        let mean = ModelDataCollection::ensemble_mean(&[gfs, ecmwf]).unwrap();
+        assert_eq!(mean.model, WeatherModel::Ensemble);
+        assert_eq!(mean.data_type, DataType::Temperature2m);
+        assert_eq!(mean.data, BTreeMap::from([(t0, 79.), (t1, 81.5)]));
+
+        let cape = ModelDataCollection {
+            model: WeatherModel::GFSSeamless,
+            data_type: DataType::Cape,
+            data: BTreeMap::new(),
+        };
+        assert!(ModelDataCollection::ensemble_mean(&[mean, cape]).is_err());
+        assert!(ModelDataCollection::ensemble_mean(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_current() {
+        let data_types = [DataType::Temperature2m, DataType::Dewpoint2m, DataType::Cape];
+        let entry = parse_current(CURRENT_FIXTURE, &data_types).unwrap();
+
+        assert_eq!(entry.date_time, "2024-07-15T18:45:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(entry.station.altitude, 27.0);
+        assert_eq!(entry.cape, Some(1250.0));
+
+        let surface = entry.surface().unwrap();
+        assert_eq!(surface.temperature, Some(84.2));
+        assert_eq!(surface.dewpoint, Some(66.1));
+
+        // derived values are filled in like the observation importers
+        let rh = surface.relative_humidity.unwrap();
+        assert!((rh - 55.).abs() < 1., "{rh}");
+        assert!(surface.heat_index.is_some());
+
+        assert!(parse_current(CURRENT_FIXTURE, &[DataType::ApparentTemperature]).is_err());
+
+        // a fetched apparent temperature survives the fill
+        let fixture = CURRENT_FIXTURE.replace(r#""temperature_2m": 84.2,"#, r#""temperature_2m": 84.2, "apparent_temperature": 91.5,"#);
+        let types = [DataType::Temperature2m, DataType::Dewpoint2m, DataType::ApparentTemperature];
+        let entry = parse_current(&fixture, &types).unwrap();
+        assert_eq!(entry.surface().unwrap().apparent_temp, Some(91.5));
+    }
 }
\ No newline at end of file
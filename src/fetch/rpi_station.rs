@@ -4,7 +4,7 @@ use chrono::{DateTime, Duration, Timelike, Utc};
 use anyhow::Result;
 use serde::Deserialize;
 
-use crate::{db::StationData, Layer, Station, WxEntry, WxEntryLayer};
+use crate::{db::StationData, fetch::raw_csv_line, Layer, Station, WxEntry, WxEntryLayer};
 
 
 // Imports data from my raspberry pi station.
@@ -37,8 +37,10 @@ pub async fn import(station_url: &str, date: DateTime<Utc>) -> Result<StationDat
 
     let mut local_db = BTreeMap::new();
 
-    for (i, record) in reader.deserialize().enumerate() {
-        match try_parse_entry(record, altitude, station.clone()) {
+    let headers = reader.headers()?.clone();
+
+    for (i, record) in reader.records().enumerate() {
+        match try_parse_entry(record, &headers, &csv_string, altitude, station.clone()) {
             Ok((dt, entry)) => {local_db.insert(dt, entry);},
             Err(e) => {eprintln!("Error parsing entry {i}: {e}");}
         }
@@ -49,8 +51,9 @@ pub async fn import(station_url: &str, date: DateTime<Utc>) -> Result<StationDat
     Ok(local_db)
 }
 
-fn try_parse_entry(record: Result<RawStationEntry, csv::Error>, altitude: f32, station: Station) -> Result<(DateTime<Utc>, WxEntry)> {
-    let record: RawStationEntry = record?;
+fn try_parse_entry(record: Result<csv::StringRecord, csv::Error>, headers: &csv::StringRecord, source: &str, altitude: f32, station: Station) -> Result<(DateTime<Utc>, WxEntry)> {
+    let raw_record = record?;
+    let record: RawStationEntry = raw_record.deserialize(Some(headers))?;
 
     let time_string = String::from(record.time) + "Z";
     let mut dt = time_string.trim().chars().filter(|x| x != &'\0').collect::<String>().parse::<DateTime<Utc>>()?;
@@ -65,6 +68,7 @@ fn try_parse_entry(record: Result<RawStationEntry, csv::Error>, altitude: f32, s
         pressure: record.raw_pres,
         wind_direction: None,
         wind_speed: None,
+        wind_gust: None,
         visibility: None,
 
         relative_humidity: None,
@@ -84,6 +88,7 @@ fn try_parse_entry(record: Result<RawStationEntry, csv::Error>, altitude: f32, s
         pressure: record.raw_pres,
         wind_direction: None,
         wind_speed: None,
+        wind_gust: None,
         visibility: None,
 
         relative_humidity: None,
@@ -108,8 +113,16 @@ fn try_parse_entry(record: Result<RawStationEntry, csv::Error>, altitude: f32, s
         cape: None,
         skycover: None,
         raw_metar: None,
+        provider_raw: raw_csv_line(source, &raw_record),
+        max_temp_today: None,
+        min_temp_today: None,
+        cloud_base_temp: None,
+        cloud_base_height: None,
         precip_today: None,
         precip: None,
+        precip_1h: None,
+        precip_6h: None,
+        precip_24h: None,
         precip_probability: None,
         wx: None,
         wx_codes: None,
@@ -1,11 +1,11 @@
 use std::collections::{BTreeMap, HashMap};
 
-use crate::{db::StationData, rh_to_dewpoint, Direction, Layer, Precip, Station, WxEntry, WxEntryLayer};
+use crate::{db::StationData, fetch::raw_csv_line, rh_to_dewpoint, Layer, Precip, Station, Wind, WxEntry, WxEntryLayer};
 
 use chrono::{offset::LocalResult, DateTime, Datelike, Local, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::US::Eastern;
 use serde::Deserialize;
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 pub async fn import(date: DateTime<Utc>) -> Result<StationData> {
 
@@ -21,9 +21,20 @@ pub async fn import(date: DateTime<Utc>) -> Result<StationData> {
 
     let mut db = BTreeMap::new();
 
-    for entry_result in rdr.deserialize() {
-        let entry: UNHWxEntry = entry_result?;
-        let wx_entry: WxEntry = entry.to_wx_entry();
+    let headers = rdr.headers()?.clone();
+
+    for (i, record_result) in rdr.records().enumerate() {
+        let record = record_result?;
+        let entry: UNHWxEntry = record.deserialize(Some(&headers))?;
+        // a bad row is reported and skipped rather than failing the whole day
+        let mut wx_entry: WxEntry = match entry.to_wx_entry() {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("Error parsing UNH entry {i}: {e}");
+                continue;
+            }
+        };
+        wx_entry.provider_raw = raw_csv_line(&unh_text, &record);
 
         db.insert(wx_entry.date_time, wx_entry);
     }
@@ -87,7 +98,13 @@ struct UNHWxEntry {
 }
 
 impl UNHWxEntry {
-    fn to_wx_entry(self) -> WxEntry {
+    // errors on a wind direction outside 0-360° instead of dropping it
+    fn to_wx_entry(self) -> Result<WxEntry> {
+        if self.wind_dir.is_nan() || self.wind_dir < 0. {
+            bail!("Invalid UNH wind direction {} at {}", self.wind_dir, self.dt)
+        }
+        let wind = Wind::new(self.wind_speed, self.wind_dir.round() as u16)?;
+
         let unh_station = Station {
             name: "UNH".into(),
             altitude: 28.0, //meters
@@ -103,8 +120,9 @@ impl UNHWxEntry {
             temperature: Some(self.temperature_2m), 
             dewpoint: Some(rh_to_dewpoint(self.temperature_2m, self.relative_humidity)), 
             pressure: None, 
-            wind_direction: Direction::from_degrees(self.wind_dir as u16).ok(), 
-            wind_speed: Some(self.wind_speed), 
+            wind_direction: Some(wind.direction), 
+            wind_speed: Some(wind.speed), 
+            wind_gust: None,
             visibility: None,
 
             relative_humidity: None,
@@ -122,13 +140,21 @@ impl UNHWxEntry {
             cape: None, 
             skycover: None, 
             wx_codes: None, 
+            max_temp_today: None,
+            min_temp_today: None,
+            cloud_base_temp: None,
+            cloud_base_height: None,
             raw_metar: None, 
+            provider_raw: None,
             precip_today: None, 
             precip: Some(Precip {
                 rain: self.rain,
                 snow: 0.,
                 unknown: 0.,
             }), 
+            precip_1h: None,
+            precip_6h: None,
+            precip_24h: None,
             precip_probability: None,
             altimeter: None,
             
@@ -138,7 +164,38 @@ impl UNHWxEntry {
 
         entry.fill_in_calculated_values();
 
-        return entry
+        Ok(entry)
+
+    }
+}
+
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unh_entry(wind_dir: f32) -> UNHWxEntry {
+        UNHWxEntry {
+            dt: "2024-07-15T18:45:00Z".parse().unwrap(),
+            wind_speed: 8.,
+            temperature_2m: 80.,
+            rain: 0.,
+            relative_humidity: 50.,
+            wind_dir,
+        }
+    }
+
+    #[test]
+    fn test_to_wx_entry_wind_direction() {
+        let entry = unh_entry(225.).to_wx_entry().unwrap();
+        assert_eq!(entry.surface().unwrap().wind_direction.unwrap().degrees(), 230);
+
+        // rounded, not truncated, before snapping to 10°
+        let entry = unh_entry(234.9).to_wx_entry().unwrap();
+        assert_eq!(entry.surface().unwrap().wind_direction.unwrap().degrees(), 240);
+
+        assert!(unh_entry(400.).to_wx_entry().is_err());
+        assert!(unh_entry(-10.).to_wx_entry().is_err());
+        assert!(unh_entry(f32::NAN).to_wx_entry().is_err());
     }
-}
\ No newline at end of file
+}
@@ -8,11 +8,42 @@ const Md: f32 = 28.96546e-3; // kg/mol
 #[allow(non_upper_case_globals)]
 const Rd: f32 = R / Md; // (J K-1 kg-1)
 
+// coefficients for the Magnus approximation of saturation vapor pressure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MagnusCoeffs {
+    pub beta: f32, // constant
+    pub lambda: f32, // degrees C
+}
+
+// over water, as recommended by the WMO
+pub const MAGNUS_WMO: MagnusCoeffs = MagnusCoeffs { beta: 17.62, lambda: 243.12 };
+// over ice, for frost points. rh must be relative to ice.
+pub const MAGNUS_ICE: MagnusCoeffs = MagnusCoeffs { beta: 22.46, lambda: 272.62 };
+
+// saturation vapor pressure (hPa) for temperature in °F
+pub fn saturation_vapor_pressure_with(temp: f32, coeffs: MagnusCoeffs) -> f32 {
+    let t_c = f_to_c(temp);
+    6.112 * ((coeffs.beta * t_c) / (coeffs.lambda + t_c)).exp()
+}
+
+pub fn saturation_vapor_pressure(temp: f32) -> f32 {
+    saturation_vapor_pressure_with(temp, MAGNUS_WMO)
+}
+
+pub fn saturation_vapor_pressure_ice(temp: f32) -> f32 {
+    saturation_vapor_pressure_with(temp, MAGNUS_ICE)
+}
+
 pub fn rh_to_dewpoint(temp: f32, rh: f32) -> f32 {
+    dewpoint_from_rh_with(temp, rh, MAGNUS_WMO)
+}
+
+// temperature as °F, rh as %
+pub fn dewpoint_from_rh_with(temp: f32, rh: f32, coeffs: MagnusCoeffs) -> f32 {
     let t_c = f_to_c(temp);
     
-    let beta = 17.62; // constant
-    let lambda = 243.12; // degrees C
+    let beta = coeffs.beta;
+    let lambda = coeffs.lambda;
     
     let ln_rh = (rh/100.).ln();
     let temp_term = (beta*t_c)/(lambda+t_c);
@@ -23,6 +54,16 @@ pub fn rh_to_dewpoint(temp: f32, rh: f32) -> f32 {
     c_to_f(dp_c)
 }
 
+// rounds to the nearest multiple of step, e.g. for displaying a feels-like
+// temperature to the whole degree. values are rounded in whatever unit they
+// are given in, so convert first if needed.
+pub fn round_to_nearest(value: f32, step: f32) -> f32 {
+    if step <= 0. {
+        return value;
+    }
+    (value / step).round() * step
+}
+
 pub fn c_to_f(f: f32) -> f32 {
     (f * 9./5.) + 32.
 }
@@ -56,6 +97,40 @@ pub fn kts_to_kph(f: f32) -> f32 {
 }
 
 
+// force on the beaufort scale, from wind speed in kts
+pub fn beaufort(kts: f32) -> u8 {
+    const UPPER_BOUNDS: [f32; 12] = [1., 4., 7., 11., 17., 22., 28., 34., 41., 48., 56., 64.];
+    let kts = kts.round();
+    UPPER_BOUNDS.iter().position(|b| kts < *b).unwrap_or(12) as u8
+}
+
+pub fn beaufort_description(force: u8) -> &'static str {
+    match force {
+        0 => "Calm",
+        1 => "Light air",
+        2 => "Light breeze",
+        3 => "Gentle breeze",
+        4 => "Moderate breeze",
+        5 => "Fresh breeze",
+        6 => "Strong breeze",
+        7 => "Near gale",
+        8 => "Gale",
+        9 => "Strong gale",
+        10 => "Storm",
+        11 => "Violent storm",
+        _ => "Hurricane force",
+    }
+}
+
+// gust speed over mean speed. None when calm
+pub fn gust_factor(speed: f32, gust: f32) -> Option<f32> {
+    if speed <= 0. {
+        None
+    } else {
+        Some(gust / speed)
+    }
+}
+
 #[allow(non_snake_case)]
 pub fn hpa_to_inhg(h: f32) -> f32 {
     h*0.02952998057228486
@@ -86,6 +161,79 @@ pub fn distance_between_coords_km(lat1: f32, long1: f32, lat2: f32, long2: f32)
     d
 } 
 
+// hours between sunrise and sunset, using the sun's upper limb with standard
+// refraction (-0.833°). clamps to 0 or 24 hours during polar night/day.
+pub fn day_length_hours(latitude: f32, day_of_year: u32) -> f32 {
+    let phi = latitude * PI / 180.;
+    let declination = (-23.44 * PI / 180.) * (2. * PI / 365. * (day_of_year as f32 + 10.)).cos();
+    let sun_altitude: f32 = -0.833 * PI / 180.;
+
+    let cos_hour_angle = (sun_altitude.sin() - phi.sin() * declination.sin()) / (phi.cos() * declination.cos());
+
+    if cos_hour_angle >= 1. {
+        0. // sun never rises
+    } else if cos_hour_angle <= -1. {
+        24. // sun never sets
+    } else {
+        2. * cos_hour_angle.acos() * 180. / PI / 15.
+    }
+}
+
+// Vincenty's inverse formula on the WGS84 ellipsoid. more accurate than the
+// spherical distance_between_coords_km over long baselines, but slower.
+// falls back to the spherical distance if it fails to converge (nearly
+// antipodal points).
+pub fn distance_between_coords_ellipsoid_km(lat1: f32, long1: f32, lat2: f32, long2: f32) -> f32 {
+    const A: f64 = 6378137.0; // semi-major axis, m
+    const F: f64 = 1. / 298.257223563; // flattening
+    const B: f64 = A * (1. - F); // semi-minor axis, m
+
+    let to_rad = |x: f32| (x as f64).to_radians();
+
+    let l = to_rad(long2) - to_rad(long1);
+    let u1 = ((1. - F) * to_rad(lat1).tan()).atan();
+    let u2 = ((1. - F) * to_rad(lat2).tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2) + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2)).sqrt();
+        if sin_sigma == 0. {
+            return 0.; // same point
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1. - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos_sq_alpha != 0. {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos_sq_alpha
+        } else {
+            0. // along the equator
+        };
+        let c = F / 16. * cos_sq_alpha * (4. + F * (4. - 3. * cos_sq_alpha));
+
+        let lambda_prev = lambda;
+        lambda = l + (1. - c) * F * sin_alpha *
+            (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1. + 2. * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - lambda_prev).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (A * A - B * B) / (B * B);
+            let a_coef = 1. + u_sq / 16384. * (4096. + u_sq * (-768. + u_sq * (320. - 175. * u_sq)));
+            let b_coef = u_sq / 1024. * (256. + u_sq * (-128. + u_sq * (74. - 47. * u_sq)));
+            let delta_sigma = b_coef * sin_sigma * (cos_2sigma_m + b_coef / 4. *
+                (cos_sigma * (-1. + 2. * cos_2sigma_m * cos_2sigma_m) -
+                 b_coef / 6. * cos_2sigma_m * (-3. + 4. * sin_sigma * sin_sigma) * (-3. + 4. * cos_2sigma_m * cos_2sigma_m)));
+
+            let meters = B * a_coef * (sigma - delta_sigma);
+            return (meters / 1000.) as f32;
+        }
+    }
+
+    distance_between_coords_km(lat1, long1, lat2, long2)
+}
+
 pub fn altimeter_to_station(altimeter: f32, height: f32) -> f32 {
     let height = height as f64;
     let altimeter = altimeter as f64;
@@ -130,17 +278,104 @@ pub fn lcl_temperature(temperature_kelvin_below_lcl: f32, dewpoint_kelvin: f32)
 
 
 
-// an approximation
+// an approximation, after Bolton (1980)
+// note that mixing_ratio_g_kg and vapor_pressure are called with the
+// *dewpoint*: the saturation vapor pressure at the dewpoint is the actual
+// vapor pressure of the air, so this gives the actual (not saturation)
+// mixing ratio.
 pub fn theta_e(temperature_kelvin_below_lcl: f32, dewpoint_kelvin: f32, station_pressure: f32) -> f32 {
-    const p_0: f32 = 1000.0;
+    const P_0: f32 = 1000.0;
     // source: https://en.wikipedia.org/wiki/Equivalent_potential_temperature
     let vap_pres = vapor_pressure(dewpoint_kelvin);
     let t_l: f32 = lcl_temperature(temperature_kelvin_below_lcl, dewpoint_kelvin); // temperature at LCL
     let r: f32 = mixing_ratio_g_kg(dewpoint_kelvin, station_pressure) / 1000.0; // mixing ratio in kg/kg
     let theta_l: f32 = temperature_kelvin_below_lcl * 
-                        ((p_0/(station_pressure - vap_pres)).powf(0.2854)) *
+                        ((P_0/(station_pressure - vap_pres)).powf(0.2854)) *
                         ((temperature_kelvin_below_lcl/t_l).powf(0.28*r)); // dry potential temperature at LCL
     let theta_e = theta_l * (((3036.0/t_l) - 1.78) * r * (1.0 + (0.448*r))).exp();
 
     return theta_e
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(val: f32, cmp: f32, tolerance: f32) -> bool {
+        if (val - cmp).abs() < tolerance {
+            true
+        } else {
+            println!("{val}");
+            false
+        }
+    }
+
+    #[test]
+    fn test_ellipsoid_distance() {
+        // JFK to LAX
+        let (lat1, long1, lat2, long2) = (40.6413, -73.7781, 33.9416, -118.4085);
+        let sphere = distance_between_coords_km(lat1, long1, lat2, long2);
+        let ellipsoid = distance_between_coords_ellipsoid_km(lat1, long1, lat2, long2);
+
+        assert!(ellipsoid > 3980. && ellipsoid < 3986., "{ellipsoid}");
+        assert!((ellipsoid - sphere).abs() / ellipsoid < 0.005, "{sphere} {ellipsoid}");
+
+        assert_eq!(distance_between_coords_ellipsoid_km(lat1, long1, lat1, long1), 0.);
+    }
+
+    #[test]
+    fn test_magnus_presets() {
+        let temp = c_to_f(-10.);
+
+        let dewpoint = f_to_c(dewpoint_from_rh_with(temp, 80., MAGNUS_WMO));
+        let frost_point = f_to_c(dewpoint_from_rh_with(temp, 80., MAGNUS_ICE));
+        assert!(close(dewpoint, -12.80, 0.05));
+        assert!(close(frost_point, -12.49, 0.05));
+        assert!(frost_point > dewpoint);
+
+        // saturated air sits at the air temperature either way
+        assert!(close(dewpoint_from_rh_with(temp, 100., MAGNUS_ICE), temp, 1e-3));
+
+        assert_eq!(rh_to_dewpoint(temp, 80.), dewpoint_from_rh_with(temp, 80., MAGNUS_WMO));
+    }
+
+    #[test]
+    fn test_theta_e() {
+        // 25°C, 20°C dewpoint, 1000 hPa. reference worked by hand from
+        // Bolton (1980) with the same vapor pressure formula: 341.6 K
+        let moist = theta_e(c_to_k(25.), c_to_k(20.), 1000.);
+        assert!(close(moist, 341.6, 0.5));
+
+        // dry air has theta_e close to the plain potential temperature
+        let dry = theta_e(c_to_k(25.), c_to_k(-40.), 1000.);
+        assert!(close(dry, c_to_k(25.), 0.5));
+    }
+
+    #[test]
+    fn test_round_to_nearest() {
+        // temperatures
+        assert_eq!(round_to_nearest(82.83, 1.), 83.);
+        assert_eq!(round_to_nearest(-30.7, 1.), -31.);
+        assert_eq!(round_to_nearest(f_to_c(83.5), 0.5), 28.5);
+
+        // pressures
+        assert!(close(round_to_nearest(1013.26, 0.1), 1013.3, 1e-3));
+        assert_eq!(round_to_nearest(1013.26, 2.), 1014.);
+        assert!(close(round_to_nearest(hpa_to_inhg(1013.25), 0.01), 29.92, 1e-4));
+
+        // a zero step leaves the value alone
+        assert_eq!(round_to_nearest(82.83, 0.), 82.83);
+    }
+
+    #[test]
+    fn test_saturation_vapor_pressure() {
+        // both curves meet at the triple point
+        assert!(close(saturation_vapor_pressure(32.), 6.112, 1e-4));
+        assert!(close(saturation_vapor_pressure_ice(32.), 6.112, 1e-4));
+
+        // -10°C: 2.87 hPa over water, 2.60 hPa over ice
+        assert!(close(saturation_vapor_pressure(14.), 2.87, 0.01));
+        assert!(close(saturation_vapor_pressure_ice(14.), 2.60, 0.01));
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use anyhow::{Result, bail};
 
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use derive_more::Display;
 use regex::Regex;
 
@@ -18,6 +18,9 @@ use formulae::*;
 pub mod db;
 // pub use db::*;
 
+#[cfg(test)]
+mod test_util;
+
 // STRUCTS ---------------------------------------------------------------------
 
 
@@ -29,6 +32,36 @@ pub struct Station {
     pub coords: (f32, f32),
 }
 
+impl Station {
+    pub fn day_length(&self, date: NaiveDate) -> Duration {
+        let hours = day_length_hours(self.coords.0, date.ordinal());
+        Duration::seconds((hours * 3600.).round() as i64)
+    }
+
+    // degrees-minutes-seconds, e.g. 43°07'48"N 070°56'24"W
+    pub fn coords_dms_string(&self) -> String {
+        fn dms(value: f32, positive: char, negative: char, degree_width: usize) -> String {
+            let hemisphere = if value < 0. { negative } else { positive };
+            let total_seconds = (value.abs() as f64 * 3600.).round() as u32;
+            let (d, m, s) = (total_seconds / 3600, total_seconds / 60 % 60, total_seconds % 60);
+            format!("{d:0degree_width$}°{m:02}'{s:02}\"{hemisphere}")
+        }
+
+        format!("{} {}", dms(self.coords.0, 'N', 'S', 2), dms(self.coords.1, 'E', 'W', 3))
+    }
+
+    // station pressure (QFE, hPa) for an altimeter setting (QNH, hPa) at this
+    // station's elevation
+    pub fn qfe_from_altimeter(&self, altimeter: f32) -> f32 {
+        altimeter_to_station(altimeter, self.altitude)
+    }
+
+    // fraction of the day (0 to 1) that the sun is up
+    pub fn daylight_fraction(&self, date: NaiveDate) -> f32 {
+        day_length_hours(self.coords.0, date.ordinal()) / 24.
+    }
+}
+
 
 
 // WXENTRY
@@ -38,6 +71,8 @@ pub struct WxEntry {
     pub date_time: DateTime<Utc>,
     pub station: Station,
     
+    // keyed by Layer::key() in JSON, which needs string map keys
+    #[serde(with = "layer_map")]
     pub layers: HashMap<Layer, WxEntryLayer>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -50,12 +85,42 @@ pub struct WxEntry {
     pub wx: Option<Wx>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_metar: Option<String>,
+    // the provider's original payload for this entry (JSON, CSV line, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_raw: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_temp_today: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_temp_today: Option<f32>,
+    // temperature (°F) at the lowest cloud base, height in feet AGL like
+    // CloudLayer. kept out of the layers so it is never mistaken for a
+    // near-surface value by best_temperature and friends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud_base_temp: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud_base_height: Option<u32>,
+    // cumulative since local midnight
     #[serde(skip_serializing_if = "Option::is_none")]
     pub precip_today: Option<Precip>,
+    // incremental, fallen since the previous entry
     #[serde(skip_serializing_if = "Option::is_none")]
     pub precip: Option<Precip>,
+    // totals over the period ending at this entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precip_1h: Option<Precip>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precip_6h: Option<Precip>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precip_24h: Option<Precip>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub precip_probability: Option<f32>,
+    // three different pressures, all in hPa:
+    // - altimeter: the altimeter setting (QNH), station pressure reduced to
+    //   sea level through the standard atmosphere
+    // - station pressure (QFE): the actual pressure at the station's
+    //   elevation, see best_pressure and Station::qfe_from_altimeter
+    // - best_slp: mean sea level pressure (MSLP), reduced using the observed
+    //   temperature
     #[serde(skip_serializing_if = "Option::is_none")]
     pub altimeter: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -73,12 +138,20 @@ impl WxEntry {
             
             cape: None,
             skycover: None,
+            max_temp_today: None,
+            min_temp_today: None,
+            cloud_base_temp: None,
+            cloud_base_height: None,
             precip_today: None,
             precip: None,
+            precip_1h: None,
+            precip_6h: None,
+            precip_24h: None,
             precip_probability: None,
             wx_codes: None,
             wx: None,
             raw_metar: None,
+            provider_raw: None,
             altimeter: None,
             
             best_slp: None,
@@ -90,14 +163,99 @@ impl WxEntry {
     }
 
     pub fn best_slp(&self) -> Option<f32> {
+        self.best_slp_with(SlpOptions::default())
+    }
+
+    pub fn best_slp_with(&self, options: SlpOptions) -> Option<f32> {
         let option_1 = {self.layers.get(&SeaLevel).map(|x| x.pressure).flatten()};
-        let option_2 = {self.layers.get(&NearSurface).map(|x| x.slp(self.latitude())).flatten()};
-        let option_3 = {self.layers.get(&Indoor).map(|x| x.slp(self.latitude())).flatten()};
+        let option_2 = {self.layers.get(&NearSurface).map(|x| x.slp_with(self.latitude(), options)).flatten()};
+        let option_3 = {self.layers.get(&Indoor).map(|x| x.slp_with(self.latitude(), options)).flatten()};
         let option_4 = {self.altimeter_to_slp()};
 
         option_1.or(option_2).or(option_3).or(option_4)
     }
 
+    // outdoor layers closest to the ground, in order of preference:
+    // NearSurface first, then any AGL layers from lowest to highest
+    fn surface_layer_priority(&self) -> Vec<Layer> {
+        let mut agl: Vec<Layer> = self.layers.keys().filter(|l| matches!(l, AGL(_))).cloned().collect();
+        agl.sort_by_key(|l| match l {
+            AGL(h) => *h,
+            _ => unreachable!(),
+        });
+
+        let mut priority = vec![NearSurface];
+        priority.extend(agl);
+        priority
+    }
+
+    fn first_available<T, F: Fn(&WxEntryLayer) -> Option<T>>(&self, priority: &[Layer], f: F) -> Option<T> {
+        priority.iter()
+            .filter_map(|l| self.layers.get(l))
+            .find_map(f)
+    }
+
+    // tries NearSurface, then the lowest AGL layer upwards
+    pub fn best_temperature(&self) -> Option<f32> {
+        self.first_available(&self.surface_layer_priority(), |x| x.temperature)
+    }
+
+    // tries NearSurface, then the lowest AGL layer upwards
+    pub fn best_wind(&self) -> Option<Wind> {
+        self.first_available(&self.surface_layer_priority(), |x| x.wind())
+    }
+
+    // entry-wide params only, see WxEntryLayer::has for the rest
+    pub fn has(&self, param: Param) -> bool {
+        match param {
+            Param::Cape => self.cape.is_some(),
+            Param::SkyCover => self.skycover.is_some(),
+            Param::WxCodes => self.wx_codes.is_some(),
+            Param::RawMetar => self.raw_metar.is_some(),
+            Param::Altimeter => self.altimeter.is_some(),
+            Param::MaxTempToday => self.max_temp_today.is_some(),
+            Param::MinTempToday => self.min_temp_today.is_some(),
+            Param::CloudBaseTemp => self.cloud_base_temp.is_some(),
+            Param::PrecipToday => self.precip_today.is_some(),
+            Param::Precip => self.precip.is_some(),
+            Param::Precip1h => self.precip_1h.is_some(),
+            Param::Precip6h => self.precip_6h.is_some(),
+            Param::Precip24h => self.precip_24h.is_some(),
+            Param::PrecipProbability => self.precip_probability.is_some(),
+            _ => false, // per layer
+        }
+    }
+
+    pub fn available_fields(&self) -> Vec<Param> {
+        Param::ENTRY.iter().filter(|p| self.has(**p)).cloned().collect()
+    }
+
+    // vector average of the winds in the given layers, skipping layers without wind
+    pub fn mean_wind(&self, layers: &[Layer]) -> Option<Wind> {
+        let components: Vec<(f32, f32)> = layers.iter()
+            .filter_map(|l| self.layers.get(l)?.wind())
+            .map(|w| w.components())
+            .collect();
+
+        if components.is_empty() {
+            return None;
+        }
+
+        let n = components.len() as f32;
+        let (u, v) = components.iter().fold((0., 0.), |acc, c| (acc.0 + c.0, acc.1 + c.1));
+        Some(Wind::from_components(u / n, v / n))
+    }
+
+    // station pressure. tries NearSurface, then the lowest AGL layer upwards,
+    // then Indoor, and finally falls back to the altimeter setting
+    pub fn best_pressure(&self) -> Option<f32> {
+        let mut priority = self.surface_layer_priority();
+        priority.push(Indoor);
+
+        self.first_available(&priority, |x| x.pressure)
+            .or(self.altimeter_to_station())
+    }
+
     pub fn altimeter_to_station(&self) -> Option<f32> {
         Some(altimeter_to_station(self.altimeter?, self.station.altitude))
     }
@@ -136,9 +294,79 @@ impl WxEntry {
             e.1.fill_in_calculated_values(lat, alt);
         }
 
-        self.wx_from_codes();
+        self.wx = self.wx_from_codes();
         self.best_slp = self.best_slp();
     }
+
+    // physically impossible values and combinations, sorted by layer
+    pub fn qc_issues(&self) -> Vec<QcIssue> {
+        let mut issues = vec![];
+        let lat = self.latitude();
+
+        let mut layers: Vec<&WxEntryLayer> = self.layers.values().collect();
+        layers.sort_by_key(|l| l.layer.to_string());
+
+        for l in layers {
+            let mut issue = |param: Param, message: String| {
+                issues.push(QcIssue { layer: Some(l.layer), param: Some(param), message })
+            };
+
+            if let (Some(t), Some(dp)) = (l.temperature, l.dewpoint) {
+                if dp > t {
+                    issue(Param::Dewpoint, format!("Dewpoint ({dp}) is above temperature ({t})"));
+                }
+            }
+
+            // the stored value, which some providers report without a dewpoint
+            if let Some(rh) = l.relative_humidity.filter(|rh| !(0. ..=105.).contains(rh)) {
+                issue(Param::RelativeHumidity, format!("Relative humidity ({rh:.0}%) is outside 0-105%"));
+            }
+
+            if let Some(w) = l.wind_speed.filter(|w| *w < 0.) {
+                issue(Param::WindSpeed, format!("Wind speed ({w}) is negative"));
+            }
+
+            if let Some(w) = l.wind_gust.filter(|w| *w < 0.) {
+                issue(Param::WindGust, format!("Wind gust ({w}) is negative"));
+            }
+
+            if let Some(v) = l.visibility.filter(|v| *v < 0.) {
+                issue(Param::Visibility, format!("Visibility ({v}) is negative"));
+            }
+
+            // the raw pressure against the 850-1085 hPa sea level range carried
+            // up to the layer's height through the standard atmosphere. without
+            // a height, only the sea level maximum applies.
+            let height = l.height_msl.or(match l.layer {
+                SeaLevel => Some(0.),
+                NearSurface | Indoor => Some(self.station.altitude),
+                _ => l.height_agl.map(|h| h + self.station.altitude),
+            });
+            let (low, high) = match height {
+                Some(h) => (altimeter_to_station(850., h), altimeter_to_station(1085., h)),
+                None => (0., 1085.),
+            };
+
+            if let Some(p) = l.pressure.filter(|p| !(low..=high).contains(p)) {
+                issue(Param::Pressure, format!("Pressure ({p:.1}) is outside {low:.0}-{high:.0} hPa for this height"));
+            } else if let Some(p) = l.slp(lat).filter(|p| !(850. ..=1085.).contains(p)) {
+                issue(Param::Pressure, format!("Sea level pressure ({p:.1}) is outside 850-1085 hPa"));
+            }
+        }
+
+        if let Some(a) = self.altimeter.filter(|a| !(850. ..=1085.).contains(a)) {
+            issues.push(QcIssue { layer: None, param: Some(Param::Altimeter), message: format!("Altimeter ({a:.1}) is outside 850-1085 hPa") });
+        }
+
+        issues
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QcIssue {
+    pub layer: Option<Layer>, // None for entry-wide values
+    pub param: Option<Param>,
+    pub message: String,
 }
 
 
@@ -172,6 +400,18 @@ impl fmt::Debug for WxEntry {
             parameters.push(format!("Precip Prob: {}", s.to_string()))
         }
 
+        if let Some(s) = &self.max_temp_today {
+            parameters.push(format!("High Today: {s:3.1}"))
+        }
+
+        if let Some(s) = &self.min_temp_today {
+            parameters.push(format!("Low Today: {s:3.1}"))
+        }
+
+        if let (Some(t), Some(h)) = (&self.cloud_base_temp, &self.cloud_base_height) {
+            parameters.push(format!("Cloud Base Temp: {t:3.1} at {h} ft"))
+        }
+
         if let Some(s) = &self.precip_today {
             parameters.push(s.to_string())
         }
@@ -180,6 +420,18 @@ impl fmt::Debug for WxEntry {
             parameters.push(s.to_string())
         }
 
+        if let Some(s) = &self.precip_1h {
+            parameters.push(format!("1h: {s}"))
+        }
+
+        if let Some(s) = &self.precip_6h {
+            parameters.push(format!("6h: {s}"))
+        }
+
+        if let Some(s) = &self.precip_24h {
+            parameters.push(format!("24h: {s}"))
+        }
+
         if let Some(x) = &self.raw_metar {
             parameters.push(format!("METAR: {}", x)); 
         }
@@ -226,6 +478,8 @@ pub struct WxEntryLayer {
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dewpoint: Option<f32>,
+    // in-situ pressure at this layer (hPa), never reduced. for the SeaLevel
+    // layer that is the mean sea level pressure; use slp() for a reduction.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pressure: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -233,6 +487,8 @@ pub struct WxEntryLayer {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub wind_speed: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_gust: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub visibility: Option<f32>,
 
     // #[serde(skip_serializing_if = "Option::is_none")]
@@ -263,13 +519,14 @@ impl WxEntryLayer {
     pub fn empty(layer: Layer) -> WxEntryLayer {
         WxEntryLayer {
             layer,
-            height_agl: None,
+            height_agl: layer.height_agl(),
             height_msl: None,
             temperature: None,
             dewpoint: None,
             pressure: None,
             wind_direction: None,
             wind_speed: None,
+            wind_gust: None,
             visibility: None,
 
             // wind: None,
@@ -282,17 +539,60 @@ impl WxEntryLayer {
         }
     }
 
+    pub fn has(&self, param: Param) -> bool {
+        match param {
+            Param::HeightAgl => self.height_agl.is_some(),
+            Param::HeightMsl => self.height_msl.is_some(),
+            Param::Temperature => self.temperature.is_some(),
+            Param::Dewpoint => self.dewpoint.is_some(),
+            Param::Pressure => self.pressure.is_some(),
+            Param::WindDirection => self.wind_direction.is_some(),
+            Param::WindSpeed => self.wind_speed.is_some(),
+            Param::WindGust => self.wind_gust.is_some(),
+            Param::Visibility => self.visibility.is_some(),
+            Param::RelativeHumidity => self.relative_humidity.is_some(),
+            _ => false, // entry-wide
+        }
+    }
+
+    pub fn available_fields(&self) -> Vec<Param> {
+        Param::LAYER.iter().filter(|p| self.has(**p)).cloned().collect()
+    }
+
+    // lets callers gate derived values on the data they need, e.g.
+    // [Pressure, Temperature, HeightMsl] before trusting slp()
+    pub fn has_required(&self, params: &[Param]) -> bool {
+        params.iter().all(|p| self.has(*p))
+    }
+
+    // doesn't need a wind direction, unlike wind()
+    pub fn gust_factor(&self) -> Option<f32> {
+        gust_factor(self.wind_speed?, self.wind_gust?)
+    }
+
     pub fn wind(&self) -> Option<Wind> {
         if let (Some(direction), Some(speed)) = (self.wind_direction, self.wind_speed) {
             Some(Wind {
                 direction,
-                speed
+                speed,
+                gust: self.wind_gust,
             })
         } else {
             None
         } 
     }
 
+    // relative humidity with respect to ice, in percentage. this is how
+    // humidity is reported in and near clouds below freezing (e.g. for icing
+    // or frost), and it can exceed 100% while relative_humidity, which is
+    // with respect to liquid water and what surface observations use, does
+    // not. None above freezing, where only the water value makes sense.
+    pub fn relative_humidity_ice(&self) -> Option<f32> {
+        let temp = self.temperature.filter(|t| *t <= 32.)?;
+        let vapor_pressure = saturation_vapor_pressure(self.dewpoint?);
+        Some(vapor_pressure / saturation_vapor_pressure_ice(temp) * 100.)
+    }
+
     pub fn relative_humidity(&self) -> Option<f32> { // in percentage
         if let (Some(temp_f), Some(dewp_f)) = (self.temperature, self.dewpoint) {
             let t = f_to_c(temp_f);
@@ -305,17 +605,27 @@ impl WxEntryLayer {
         }
     }
 
+    // a SeaLevel layer's pressure already is the sea level pressure, so it is
+    // returned unchanged rather than reduced again
     pub fn slp(&self, latitude: f32) -> Option<f32> {
+        self.slp_with(latitude, SlpOptions::default())
+    }
+
+    pub fn slp_with(&self, latitude: f32, options: SlpOptions) -> Option<f32> {
+        if self.layer == SeaLevel {
+            return self.pressure;
+        }
+
         if let (Some(p), Some(t), Some(h)) = (self.pressure, self.temperature, self.height_msl) {
             // http://www.wind101.net/sea-level-pressure-advanced/sea-level-pressure-advanced.html
             let phi =  latitude * PI / 180.0;
-            let b = 1013.25; //(average baro pressure of a column)
+            let b = options.column_pressure;
             let k_upper =  18400.; // meters apparently
             let alpha = 0.0037; // coefficient of thermal expansion of air
             let k_lower = 0.0026; // based on figure of earth
             let r = 6367324.; // radius of earth
             
-            let lapse_rate = 0.005; // 0.5C/100m
+            let lapse_rate = options.lapse_rate;
 
             let column_temp = f_to_c(t) + (lapse_rate*h)/2.; // take the average of the temperature
             // dbg!(&column_temp);
@@ -429,6 +739,31 @@ impl WxEntryLayer {
         }
     }
 
+    // None - Incomplete Data
+    // Some(HeatAdvisory::None) - below the thresholds, or the heat index is
+    // outside its valid range
+    pub fn heat_advisory(&self) -> Option<HeatAdvisory> {
+        match self.heat_index_valid()? {
+            true => Some(HeatAdvisory::from_heat_index(self.heat_index()?)),
+            false => Some(HeatAdvisory::None),
+        }
+    }
+
+    // None - Incomplete Data
+    // Some(ColdAdvisory::None) - above the thresholds, or the wind chill is
+    // outside its valid range
+    pub fn wind_chill_advisory(&self) -> Option<ColdAdvisory> {
+        match self.wind_chill_valid()? {
+            true => Some(ColdAdvisory::from_wind_chill(self.wind_chill()?)),
+            false => Some(ColdAdvisory::None),
+        }
+    }
+
+    // category of the feels-like temperature, for theming
+    pub fn comfort_category(&self) -> Option<TempCategory> {
+        Some(TempCategory::from_fahrenheit(self.apparent_temp()?))
+    }
+
     pub fn theta_e(&self, altimeter: Option<f32>, altitude: Option<f32>) -> Option<f32> {
         if let (Some(temp_f), Some(dewp_f)) = (self.temperature, self.dewpoint) {
             
@@ -492,6 +827,10 @@ impl fmt::Display for WxEntryLayer {
             parameters.push(format!("Wind Speed: {}", w)); 
         }
 
+        if let Some(w) = self.wind_gust {
+            parameters.push(format!("Wind Gust: {}", w)); 
+        }
+
         if let Some(w) = self.wind_direction {
             parameters.push(format!("Wind Direction: {}", w)); 
         }
@@ -507,48 +846,241 @@ impl fmt::Display for WxEntryLayer {
     }
 }
 
+// SLP OPTIONS
+
+// assumptions used when reducing pressure to sea level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlpOptions {
+    // °C/m between the station and sea level. the fictitious column below the
+    // station is assumed warmer by lapse_rate * height / 2 on average; a
+    // larger lapse rate (e.g. near 0.0098 for well mixed air) means a warmer,
+    // less dense column and so a lower sea level pressure. use a small or
+    // negative value for strong inversions.
+    pub lapse_rate: f32,
+    pub column_pressure: f32, // hPa, average barometric pressure of the column
+}
+
+impl Default for SlpOptions {
+    fn default() -> Self {
+        SlpOptions {
+            lapse_rate: 0.005, // 0.5C/100m
+            column_pressure: 1013.25,
+        }
+    }
+}
+
+// PARAM
+
+// measured values that can be present on a layer or on the entry as a whole
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Param {
+    // layer
+    HeightAgl,
+    HeightMsl,
+    Temperature,
+    Dewpoint,
+    Pressure,
+    WindDirection,
+    WindSpeed,
+    WindGust,
+    Visibility,
+    RelativeHumidity,
+
+    // entry
+    Cape,
+    SkyCover,
+    WxCodes,
+    RawMetar,
+    Altimeter,
+    MaxTempToday,
+    MinTempToday,
+    CloudBaseTemp,
+    PrecipToday,
+    Precip,
+    Precip1h,
+    Precip6h,
+    Precip24h,
+    PrecipProbability,
+}
+
+impl Param {
+    pub const LAYER: &'static [Param] = &[
+        Param::HeightAgl, Param::HeightMsl, Param::Temperature, Param::Dewpoint,
+        Param::Pressure, Param::WindDirection, Param::WindSpeed, Param::WindGust,
+        Param::Visibility, Param::RelativeHumidity,
+    ];
+
+    pub const ENTRY: &'static [Param] = &[
+        Param::Cape, Param::SkyCover, Param::WxCodes, Param::RawMetar,
+        Param::Altimeter, Param::MaxTempToday, Param::MinTempToday,
+        Param::CloudBaseTemp, Param::PrecipToday, Param::Precip, Param::Precip1h, Param::Precip6h,
+        Param::Precip24h, Param::PrecipProbability,
+    ];
+
+    const ALL: &'static [Param] = &[
+        Param::HeightAgl, Param::HeightMsl, Param::Temperature, Param::Dewpoint,
+        Param::Pressure, Param::WindDirection, Param::WindSpeed, Param::WindGust,
+        Param::Visibility, Param::RelativeHumidity,
+        Param::Cape, Param::SkyCover, Param::WxCodes, Param::RawMetar,
+        Param::Altimeter, Param::MaxTempToday, Param::MinTempToday,
+        Param::CloudBaseTemp, Param::PrecipToday, Param::Precip, Param::Precip1h, Param::Precip6h,
+        Param::Precip24h, Param::PrecipProbability,
+    ];
+
+    // layer params followed by entry params
+    pub fn all() -> &'static [Param] {
+        Self::ALL
+    }
+
+    pub fn value_kind(&self) -> ValueKind {
+        match self {
+            Param::Temperature | Param::Dewpoint | Param::MaxTempToday
+                | Param::MinTempToday | Param::CloudBaseTemp => ValueKind::Temperature,
+            Param::Pressure | Param::Altimeter => ValueKind::Pressure,
+            Param::WindSpeed | Param::WindGust => ValueKind::Speed,
+            Param::WindDirection => ValueKind::Direction,
+            Param::HeightAgl | Param::HeightMsl => ValueKind::Height,
+            Param::Visibility => ValueKind::Distance,
+            Param::PrecipToday | Param::Precip | Param::Precip1h
+                | Param::Precip6h | Param::Precip24h => ValueKind::Precip,
+            Param::RelativeHumidity | Param::PrecipProbability => ValueKind::Percentage,
+            Param::Cape => ValueKind::Energy,
+            Param::SkyCover => ValueKind::SkyCover,
+            Param::WxCodes | Param::RawMetar => ValueKind::Text,
+        }
+    }
+}
+
+// the unit family of a Param's value, with the unit it is stored in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Temperature, // °F
+    Pressure, // hPa
+    Speed, // kts
+    Direction, // degrees
+    Height, // m
+    Distance, // miles
+    Precip, // inches
+    Percentage, // %
+    Energy, // J/kg
+    SkyCover,
+    Text,
+}
+
 // LAYER
 
-#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Layer {
     Indoor,
     NearSurface,
     SeaLevel,
-    AGL(u64),
-    MSL(u64),
+    AGL(u64), // m
+    MSL(u64), // m
     MBAR(u64),
+    Soil(i64), // cm, negative below ground (10 cm deep is Soil(-10))
 }
 
 use Layer::*;
 
+impl Layer {
+    // string form used as a map key when serializing, e.g. "NearSurface",
+    // "AGL(10)" or "Soil(-10)". parsed back by FromStr.
+    pub fn key(&self) -> String {
+        match self {
+            Indoor => "Indoor".into(),
+            NearSurface => "NearSurface".into(),
+            SeaLevel => "SeaLevel".into(),
+            AGL(h) => format!("AGL({h})"),
+            MSL(h) => format!("MSL({h})"),
+            MBAR(h) => format!("MBAR({h})"),
+            Soil(d) => format!("Soil({d})"),
+        }
+    }
+
+    // in m, where it can be known from the layer alone
+    pub fn height_agl(&self) -> Option<f32> {
+        match self {
+            AGL(h) => Some(*h as f32),
+            Soil(d) => Some(*d as f32 / 100.),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Layer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Indoor => write!(f, "Indoor"),
             NearSurface => write!(f, "Near Surface"),
             SeaLevel => write!(f, "Sea Level"),
-            AGL(h) => write!(f, "{h} ft AGL"),
-            MSL(h) => write!(f, "{h} ft MSL"),
+            AGL(h) => write!(f, "{h} m AGL"),
+            MSL(h) => write!(f, "{h} m MSL"),
             MBAR(h) => write!(f, "{h} mb"),
+            Soil(d) if *d > 0 => write!(f, "{d} cm above ground"),
+            Soil(d) => write!(f, "{} cm below ground", -d),
         }
     }
 }
 
+impl std::str::FromStr for Layer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Layer> {
+        match s {
+            "Indoor" => return Ok(Indoor),
+            "NearSurface" => return Ok(NearSurface),
+            "SeaLevel" => return Ok(SeaLevel),
+            _ => {}
+        }
+
+        let Some((name, value)) = s.strip_suffix(')').and_then(|x| x.split_once('(')) else {
+            bail!("Unknown layer {s:?}")
+        };
+
+        Ok(match name {
+            "AGL" => AGL(value.parse()?),
+            "MSL" => MSL(value.parse()?),
+            "MBAR" => MBAR(value.parse()?),
+            "Soil" => Soil(value.parse()?),
+            _ => bail!("Unknown layer {s:?}"),
+        })
+    }
+}
+
+// serializes a layer map with Layer::key() keys, since JSON map keys must be
+// strings and most Layer variants aren't
+mod layer_map {
+    use std::collections::HashMap;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use super::Layer;
+
+    pub fn serialize<S: Serializer, V: Serialize>(map: &HashMap<Layer, V>, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(map.iter().map(|(k, v)| (k.key(), v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(d: D) -> Result<HashMap<Layer, V>, D::Error> {
+        HashMap::<String, V>::deserialize(d)?
+            .into_iter()
+            .map(|(k, v)| Ok((k.parse().map_err(D::Error::custom)?, v)))
+            .collect()
+    }
+}
+
 
 #[derive(Clone, Copy, Serialize, Deserialize, Display)]
 pub struct Direction(u16); 
 
 impl Direction {
+    // rounds to the nearest 10, halves rounding up. 355 to 360 all round to
+    // 360, which is stored as 0 (north).
     fn sanitize_degrees(degrees: u16) -> Result<u16> {
-        let degrees = if degrees > 360 {
+        if degrees > 360 {
             bail!("Degrees provided ({degrees}) were not under 360.");
-        } else if degrees % 10 != 0 {
-            ((degrees + 5) / 10) * 10 // round to nearest 10
-        } else {
-            degrees
-        };
+        }
+
+        let rounded = ((degrees as u32 + 5) / 10) * 10; // wide enough to never overflow
 
-        Ok(degrees % 360)
+        Ok((rounded % 360) as u16)
     }
 
     pub fn from_degrees(degrees: u16) -> Result<Direction> {
@@ -556,57 +1088,138 @@ impl Direction {
         Ok(Direction(corrected_degrees))
     }
 
+    // 16 point compass direction. works for any stored value, not just
+    // multiples of 10 (e.g. when deserialized)
     pub fn cardinal(&self) -> &'static str {
-        match self.0 {
-            0 => "N",
-            10 => "N",
-            20 => "NNE",
-            30 => "NNE",
-            40 => "NE",
-            50 => "NE",
-            60 => "ENE",
-            70 => "ENE",
-            80 => "E",
-            90 => "E",
-            100 => "E",
-            110 => "ESE",
-            120 => "ESE",
-            130 => "SE",
-            140 => "SE",
-            150 => "SSE",
-            160 => "SSE",
-            170 => "S",
-            180 => "S",
-            190 => "S",
-            200 => "SSW",
-            210 => "SSW",
-            220 => "SW",
-            230 => "SW",
-            240 => "WSW",
-            250 => "WSW",
-            260 => "W",
-            270 => "W",
-            280 => "W",
-            290 => "WNW",
-            300 => "WNW",
-            310 => "NW",
-            320 => "NW",
-            330 => "NNW",
-            340 => "NNW",
-            350 => "N",
-            _ => unreachable!("Direction struct contained {}, which is invalid.", self.0)
-        }
+        const POINTS: [&str; 16] = [
+            "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+            "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+        ];
+        let degrees = (self.0 % 360) as f32;
+        POINTS[(degrees / 22.5).round() as usize % 16]
     }
 
     pub fn degrees(&self) -> u16 {
         self.0
     } 
+
+    // the reciprocal direction, 180° around
+    pub fn opposite(&self) -> Direction {
+        Direction((self.0 % 360 + 180) % 360)
+    }
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Wind {
-    pub direction: Direction, // stored as degrees
+    pub direction: Direction, // where the wind is blowing from (meteorological convention)
     pub speed: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gust: Option<f32>,
+}
+
+impl Wind {
+    // errors on a direction over 360° rather than discarding it
+    pub fn new(speed: f32, direction_deg: u16) -> Result<Wind> {
+        Ok(Wind {
+            direction: Direction::from_degrees(direction_deg)?,
+            speed,
+            gust: None,
+        })
+    }
+
+    pub fn calm() -> Wind {
+        Wind { direction: Direction(0), speed: 0., gust: None }
+    }
+
+    // gust speed over mean speed. None without a gust or when calm
+    pub fn gust_factor(&self) -> Option<f32> {
+        gust_factor(self.speed, self.gust?)
+    }
+
+    // where the wind is blowing towards
+    pub fn direction_to(&self) -> Direction {
+        self.direction.opposite()
+    }
+
+    // (u, v) in kts, positive towards the east and north
+    pub fn components(&self) -> (f32, f32) {
+        let theta = (self.direction.degrees() as f32).to_radians();
+        (-self.speed * theta.sin(), -self.speed * theta.cos())
+    }
+
+    pub fn from_components(u: f32, v: f32) -> Wind {
+        let speed = u.hypot(v);
+        if speed < 1e-3 {
+            return Wind::calm();
+        }
+
+        let degrees = (-u).atan2(-v).to_degrees().rem_euclid(360.).round() as u16;
+        Wind {
+            direction: Direction::from_degrees(degrees).expect("degrees were wrapped to 0-360"),
+            speed,
+            gust: None,
+        }
+    }
+
+    // e.g. "15 kts (F4, Moderate breeze)"
+    pub fn display_beaufort(&self) -> String {
+        let force = beaufort(self.speed);
+        format!("{:.0} kts (F{}, {})", self.speed, force, beaufort_description(force))
+    }
+
+    // e.g. "230° @ 15 mph G 25 mph"
+    pub fn display_in(&self, unit: SpeedUnit, decimals: usize) -> String {
+        let u = unit.abbreviation();
+        let mut s = format!("{}° @ {:.decimals$} {u}", self.direction.degrees(), unit.convert_kts(self.speed));
+        if let Some(gust) = self.gust {
+            s += &format!(" G {:.decimals$} {u}", unit.convert_kts(gust));
+        }
+        s
+    }
+
+    // METAR wind group, e.g. "23015KT" or "23015G25KT". the direction is
+    // rounded to the nearest ten degrees, with north as 360 and calm as 00000KT
+    pub fn to_metar(&self) -> String {
+        let speed = self.speed.round() as u32;
+        if speed == 0 {
+            return "00000KT".into();
+        }
+
+        let direction = match (self.direction.degrees() as u32 + 5) / 10 * 10 {
+            0 => 360,
+            d => d,
+        };
+
+        match self.gust.map(|g| g.round() as u32).filter(|g| *g > speed) {
+            Some(gust) => format!("{direction:03}{speed:02}G{gust:02}KT"),
+            None => format!("{direction:03}{speed:02}KT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedUnit {
+    Knots,
+    Mph,
+    Kph,
+}
+
+impl SpeedUnit {
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Knots => "kts",
+            Self::Mph => "mph",
+            Self::Kph => "km/h",
+        }
+    }
+
+    fn convert_kts(&self, x: f32) -> f32 {
+        match self {
+            Self::Knots => x,
+            Self::Mph => kts_to_mph(x),
+            Self::Kph => kts_to_kph(x),
+        }
+    }
 }
 
 impl Display for Wind {
@@ -616,7 +1229,7 @@ impl Display for Wind {
 }
 
 
-#[derive(Serialize, Deserialize, Clone, Copy, Display, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Display, PartialEq)]
 pub enum CloudLayerCoverage {
     #[display(fmt = "FEW")]
     Few,
@@ -629,6 +1242,39 @@ pub enum CloudLayerCoverage {
 }
 
 impl CloudLayerCoverage {
+    // upper end of the okta range each code covers
+    pub fn oktas(&self) -> u8 {
+        match self {
+            Self::Few => 2,
+            Self::Scattered => 4,
+            Self::Broken => 7,
+            Self::Overcast => 8,
+        }
+    }
+
+    // None for a clear sky (0 oktas) or anything over 8
+    pub fn from_oktas(oktas: u8) -> Option<Self> {
+        match oktas {
+            1..=2 => Some(Self::Few),
+            3..=4 => Some(Self::Scattered),
+            5..=7 => Some(Self::Broken),
+            8 => Some(Self::Overcast),
+            _ => None,
+        }
+    }
+
+    // sky cover fraction from 0 to 1, e.g. model cloud cover / 100. any cloud
+    // at all is at least FEW, and only complete cover is OVC.
+    pub fn from_fraction(fraction: f32) -> Option<Self> {
+        if fraction.is_nan() || fraction <= 0. {
+            return None;
+        }
+        if fraction >= 1. {
+            return Some(Self::Overcast);
+        }
+        Self::from_oktas((fraction * 8.).round().clamp(1., 7.) as u8)
+    }
+
     pub fn str(&self) -> &'static str {
         match self {
             Self::Few => "FEW",
@@ -673,11 +1319,37 @@ impl fmt::Display for CloudLayer {
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(untagged)]
+#[serde(from = "Option<Vec<CloudLayer>>")]
 pub enum SkyCoverage {
     Clear,
     Cloudy(Vec<CloudLayer>),
 }
 
+impl SkyCoverage {
+    // an empty list of cloud layers is a clear sky
+    pub fn from_layers(layers: Vec<CloudLayer>) -> SkyCoverage {
+        if layers.is_empty() {
+            Self::Clear
+        } else {
+            Self::Cloudy(layers)
+        }
+    }
+
+    // coverage of the most covered layer
+    pub fn oktas(&self) -> u8 {
+        match self {
+            Self::Clear => 0,
+            Self::Cloudy(v) => v.iter().map(|x| x.coverage.oktas()).max().unwrap_or(0),
+        }
+    }
+}
+
+impl From<Option<Vec<CloudLayer>>> for SkyCoverage {
+    fn from(layers: Option<Vec<CloudLayer>>) -> Self {
+        SkyCoverage::from_layers(layers.unwrap_or_default())
+    }
+}
+
 impl fmt::Display for SkyCoverage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -689,6 +1361,7 @@ impl fmt::Display for SkyCoverage {
     }
 }
 
+// amounts are in inches
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Precip {
     pub unknown: f32,
@@ -696,6 +1369,66 @@ pub struct Precip {
     pub snow: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrecipUnit {
+    Inches,
+    Millimeters,
+}
+
+impl PrecipUnit {
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Inches => "in",
+            Self::Millimeters => "mm",
+        }
+    }
+
+    fn convert_inches(&self, x: f32) -> f32 {
+        match self {
+            Self::Inches => x,
+            Self::Millimeters => x * 25.4,
+        }
+    }
+}
+
+impl Precip {
+    // every amount converted from inches into unit. the result is only for
+    // display; WxEntry fields are always in inches.
+    pub fn convert(&self, unit: PrecipUnit) -> Precip {
+        Precip {
+            unknown: unit.convert_inches(self.unknown),
+            rain: unit.convert_inches(self.rain),
+            snow: unit.convert_inches(self.snow),
+        }
+    }
+
+    pub fn display_in(&self, unit: PrecipUnit) -> String {
+        let c = self.convert(unit);
+        let u = unit.abbreviation();
+        format!("Rain: {:.2} {u}, Snow: {:.2} {u}, Unknown: {:.2} {u}", c.rain, c.snow, c.unknown)
+    }
+
+    // differences a cumulative reading (like precip_today) against the previous
+    // one to get the amount that fell in between. a drop means the counter was
+    // reset (e.g. at local midnight), in which case everything in the current
+    // reading fell since the reset. never negative.
+    pub fn as_incremental(&self, prev: &Precip) -> Precip {
+        fn difference(current: f32, prev: f32) -> f32 {
+            if current < prev {
+                current.max(0.)
+            } else {
+                current - prev
+            }
+        }
+
+        Precip {
+            unknown: difference(self.unknown, prev.unknown),
+            rain: difference(self.rain, prev.rain),
+            snow: difference(self.snow, prev.snow),
+        }
+    }
+}
+
 impl Display for Precip {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Rain: {}, Snow: {}, Unknown: {}", self.rain, self.snow, self.unknown)
@@ -779,6 +1512,17 @@ impl Wx {
         }
     }
 
+    // heaviest falling precipitation at the station. precipitation that is
+    // only nearby counts as none.
+    pub fn precip_intensity(&self) -> Intensity {
+        let heaviest = self.rain
+            .most_intense(self.snow)
+            .most_intense(self.falling_ice)
+            .most_intense(self.unknown);
+
+        if heaviest == Intensity::Nearby { Intensity::None } else { heaviest }
+    }
+
     pub fn parse_code(code: &str) -> Wx {
         let re = Regex::new(r"(-|\+|BC|BL|BR|DR|DS|DU|DZ|FC|FG|FU|FZ|GR|GS|HZ|IC|MI|NSW|PL|PO|PR|PY|RA|SA|SG|SH|SN|SQ|SS|TS|UP|VA|VC|/+)").unwrap();
         
@@ -847,6 +1591,86 @@ impl Wx {
     }
 }
 
+// bands of (apparent) temperature in °F, for UI theming. the lower bound of
+// each band is inclusive:
+// Frigid < 20 <= Cold < 40 <= Cool < 60 <= Mild < 75 <= Warm < 85 <= Hot < 100 <= Extreme
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TempCategory {
+    Frigid,
+    Cold,
+    Cool,
+    Mild,
+    Warm,
+    Hot,
+    Extreme,
+}
+
+impl TempCategory {
+    pub fn from_fahrenheit(temp: f32) -> TempCategory {
+        match temp {
+            t if t < 20. => Self::Frigid,
+            t if t < 40. => Self::Cold,
+            t if t < 60. => Self::Cool,
+            t if t < 75. => Self::Mild,
+            t if t < 85. => Self::Warm,
+            t if t < 100. => Self::Hot,
+            _ => Self::Extreme,
+        }
+    }
+
+    pub fn color_hex(&self) -> &'static str {
+        match self {
+            Self::Frigid => "#7b2cbf",
+            Self::Cold => "#1e6bd6",
+            Self::Cool => "#4cc3d9",
+            Self::Mild => "#5cb85c",
+            Self::Warm => "#f0ad4e",
+            Self::Hot => "#e8590c",
+            Self::Extreme => "#c92a2a",
+        }
+    }
+}
+
+// NWS heat products by heat index (°F). offices set their own criteria; these
+// are the common defaults:
+// Advisory >= 105, Warning (excessive heat warning) >= 110
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeatAdvisory {
+    None,
+    Advisory,
+    Warning,
+}
+
+impl HeatAdvisory {
+    pub fn from_heat_index(heat_index: f32) -> HeatAdvisory {
+        match heat_index {
+            h if h >= 110. => Self::Warning,
+            h if h >= 105. => Self::Advisory,
+            _ => Self::None,
+        }
+    }
+}
+
+// NWS cold products by wind chill (°F). offices set their own criteria; these
+// are the common defaults:
+// Advisory <= -15, Warning <= -25
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColdAdvisory {
+    None,
+    Advisory,
+    Warning,
+}
+
+impl ColdAdvisory {
+    pub fn from_wind_chill(wind_chill: f32) -> ColdAdvisory {
+        match wind_chill {
+            w if w <= -25. => Self::Warning,
+            w if w <= -15. => Self::Advisory,
+            _ => Self::None,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub enum Intensity {
     None,
@@ -886,8 +1710,11 @@ impl Intensity {
 
 #[cfg(test)]
 mod tests {
-    use crate::WxEntryLayer;
+    use crate::{CloudLayer, CloudLayerCoverage, ColdAdvisory, HeatAdvisory, Param, ValueKind, SlpOptions, Precip, PrecipUnit, SpeedUnit, SkyCoverage, Station, TempCategory, WxEntry, WxEntryLayer};
     use crate::Layer::*;
+    use crate::{Direction, Wind};
+    use crate::test_util::{entry_at, test_station};
+    use chrono::{Duration, NaiveDate};
 
     fn float_within_one_decimal(val: f32, cmp: f32) -> bool {
         if val < (cmp + 0.1) && val > (cmp - 0.1) {
@@ -968,5 +1795,594 @@ mod tests {
         assert!(float_within_one_decimal(apparent_temp, -58.4));
 
     }
-}
 
+
+    #[test]
+    fn test_day_length() {
+        let equator = Station {
+            name: "Equator".into(),
+            altitude: 0.,
+            coords: (0., 0.),
+        };
+        let equinox = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let length = equator.day_length(equinox);
+        assert!((length.num_minutes() - 12 * 60).abs() < 15, "{length}");
+        assert!(float_within_one_decimal(equator.daylight_fraction(equinox), 0.5));
+
+        let mut polar = equator.clone();
+        polar.coords = (80., 0.);
+        let summer = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(polar.day_length(summer), Duration::hours(24));
+        assert_eq!(polar.day_length(winter), Duration::zero());
+        assert_eq!(polar.daylight_fraction(winter), 0.);
+    }
+
+    #[test]
+    fn test_precip_incremental_across_midnight() {
+        let readings = [
+            Precip { unknown: 0.10, rain: 0., snow: 0. }, // 23:00
+            Precip { unknown: 0.25, rain: 0., snow: 0. }, // 23:30
+            Precip { unknown: 0.05, rain: 0., snow: 0. }, // 00:30, reset at midnight
+            Precip { unknown: 0.05, rain: 0., snow: 0. }, // 01:00
+        ];
+
+        let increments: Vec<f32> = readings.windows(2)
+            .map(|w| w[1].as_incremental(&w[0]).unknown)
+            .collect();
+
+        assert!((increments[0] - 0.15).abs() < 1e-6);
+        assert_eq!(increments[1], 0.05);
+        assert_eq!(increments[2], 0.);
+        assert!(increments.iter().all(|x| *x >= 0.));
+    }
+
+    #[test]
+    fn test_soil_layer() {
+        let mut e = WxEntryLayer::empty(Soil(-10));
+        e.temperature = Some(48.);
+
+        assert_eq!(Soil(-10).height_agl(), Some(-0.1));
+        assert_eq!(e.height_agl, Some(-0.1));
+        assert_eq!(Soil(-10).to_string(), "10 cm below ground");
+
+        let json = serde_json::to_string(&e).unwrap();
+        assert!(json.contains(r#""layer":{"Soil":-10}"#), "{json}");
+        let back: WxEntryLayer = serde_json::from_str(&json).unwrap();
+        assert!(back.layer == Soil(-10));
+        assert_eq!(back.temperature, Some(48.));
+
+        assert_eq!(Soil(5).to_string(), "5 cm above ground");
+        assert_eq!(Soil(0).to_string(), "0 cm below ground");
+    }
+
+    #[test]
+    fn test_entry_with_soil_layer_round_trip() {
+        let mut entry = entry_at(&test_station(), Default::default(), 70.);
+        let mut soil = WxEntryLayer::empty(Soil(-10));
+        soil.temperature = Some(48.);
+        entry.layers.insert(Soil(-10), soil);
+        entry.layers.insert(AGL(10), WxEntryLayer::empty(AGL(10)));
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""Soil(-10)":"#), "{json}");
+        assert!(json.contains(r#""NearSurface":"#), "{json}");
+
+        let back: WxEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.layers.len(), 3);
+        assert_eq!(back.layers[&Soil(-10)].temperature, Some(48.));
+        assert_eq!(back.layers[&NearSurface].temperature, Some(70.));
+
+        for layer in [Indoor, NearSurface, SeaLevel, AGL(2), MSL(100), MBAR(500), Soil(-10), Soil(5)] {
+            assert!(layer.key().parse::<crate::Layer>().unwrap() == layer);
+        }
+        assert!("Near Surface".parse::<crate::Layer>().is_err());
+        assert!("AGL(-1)".parse::<crate::Layer>().is_err());
+        assert!("Deep(1)".parse::<crate::Layer>().is_err());
+    }
+
+    #[test]
+    fn test_sky_coverage_oktas() {
+        assert_eq!(SkyCoverage::Cloudy(vec![]).oktas(), 0);
+        assert_eq!(SkyCoverage::Clear.oktas(), 0);
+
+        let layers = vec![
+            CloudLayer::from_code("FEW", 3000).unwrap().unwrap(),
+            CloudLayer::from_code("BKN", 8000).unwrap().unwrap(),
+        ];
+        assert_eq!(SkyCoverage::from_layers(layers).oktas(), 7);
+
+        assert!(matches!(SkyCoverage::from_layers(vec![]), SkyCoverage::Clear));
+        let empty: SkyCoverage = serde_json::from_str("[]").unwrap();
+        assert!(matches!(empty, SkyCoverage::Clear));
+    }
+
+    #[test]
+    fn test_has_required() {
+        let mut e = WxEntryLayer::empty(NearSurface);
+        e.temperature = Some(50.);
+        e.height_msl = Some(100.);
+
+        let slp_params = [Param::Pressure, Param::Temperature, Param::HeightMsl];
+        assert!(!e.has_required(&slp_params));
+        assert!(e.has_required(&[Param::Temperature, Param::HeightMsl]));
+        assert!(e.has_required(&[]));
+
+        e.pressure = Some(1000.);
+        assert!(e.has_required(&slp_params));
+    }
+
+    #[test]
+    fn test_display_beaufort() {
+        let mut wind = Wind {
+            direction: Direction::from_degrees(230).unwrap(),
+            speed: 15.,
+            gust: None,
+        };
+        assert_eq!(wind.display_beaufort(), "15 kts (F4, Moderate breeze)");
+
+        wind.speed = 0.2;
+        assert_eq!(wind.display_beaufort(), "0 kts (F0, Calm)");
+
+        wind.speed = 70.;
+        assert_eq!(wind.display_beaufort(), "70 kts (F12, Hurricane force)");
+    }
+
+    #[test]
+    fn test_layer_display_units() {
+        assert_eq!(MSL(1000).to_string(), "1000 m MSL");
+        assert_eq!(AGL(2).to_string(), "2 m AGL");
+        assert_eq!(AGL(2).height_agl(), Some(2.));
+        assert_eq!(MBAR(500).to_string(), "500 mb");
+    }
+
+    #[test]
+    fn test_coords_dms_string() {
+        let mut station = test_station();
+        station.coords = (43.13, -70.94);
+        assert_eq!(station.coords_dms_string(), r#"43°07'48"N 070°56'24"W"#);
+
+        station.coords = (-5.5, 2.25);
+        assert_eq!(station.coords_dms_string(), r#"05°30'00"S 002°15'00"E"#);
+    }
+
+    #[test]
+    fn test_qc_issues() {
+        let mut e = entry_at(&test_station(), Default::default(), 50.);
+        let surface = e.layers.get_mut(&NearSurface).unwrap();
+        surface.dewpoint = Some(45.);
+        surface.wind_speed = Some(5.);
+        assert!(e.qc_issues().is_empty());
+
+        e.layers.get_mut(&NearSurface).unwrap().dewpoint = Some(60.);
+        let issues = e.qc_issues();
+        assert!(issues.iter().any(|i| i.layer == Some(NearSurface) && i.param == Some(Param::Dewpoint) && i.message.contains("above temperature")));
+
+        let mut sea_level = WxEntryLayer::empty(SeaLevel);
+        sea_level.pressure = Some(1200.);
+        e.layers.insert(SeaLevel, sea_level);
+        e.layers.get_mut(&NearSurface).unwrap().wind_speed = Some(-3.);
+        let issues = e.qc_issues();
+        assert!(issues.iter().any(|i| i.layer == Some(SeaLevel) && i.param == Some(Param::Pressure)));
+        assert!(issues.iter().any(|i| i.param == Some(Param::WindSpeed)));
+        // the dewpoint above temperature is only reported once
+        assert_eq!(issues.iter().filter(|i| i.param == Some(Param::Dewpoint)).count(), 1);
+        assert_eq!(issues.iter().filter(|i| i.param == Some(Param::Pressure)).count(), 1);
+    }
+
+    #[test]
+    fn test_param_value_kind() {
+        assert_eq!(Param::Temperature.value_kind(), ValueKind::Temperature);
+        assert_eq!(Param::Altimeter.value_kind(), ValueKind::Pressure);
+        assert_eq!(Param::WindGust.value_kind(), ValueKind::Speed);
+        assert_eq!(Param::Precip6h.value_kind(), ValueKind::Precip);
+
+        let all = Param::all();
+        assert_eq!(all.len(), Param::LAYER.len() + Param::ENTRY.len());
+        assert!(Param::LAYER.iter().chain(Param::ENTRY).all(|p| all.contains(p)));
+    }
+
+    #[test]
+    fn test_qc_relative_humidity_and_altimeter() {
+        let mut e = WxEntry::empty(&test_station());
+        let mut surface = WxEntryLayer::empty(NearSurface);
+        surface.relative_humidity = Some(-5.);
+        e.layers.insert(NearSurface, surface);
+        let issues = e.qc_issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].param, Some(Param::RelativeHumidity));
+
+        e.layers.get_mut(&NearSurface).unwrap().relative_humidity = Some(110.);
+        assert_eq!(e.qc_issues()[0].param, Some(Param::RelativeHumidity));
+
+        e.layers.get_mut(&NearSurface).unwrap().relative_humidity = Some(100.);
+        assert!(e.qc_issues().is_empty());
+
+        e.altimeter = Some(700.);
+        let issues = e.qc_issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].layer, None);
+        assert_eq!(issues[0].param, Some(Param::Altimeter));
+    }
+
+    #[test]
+    fn test_qc_raw_pressure() {
+        let mut e = WxEntry::empty(&test_station());
+        let mut surface = WxEntryLayer::empty(NearSurface);
+        surface.pressure = Some(1010.);
+        e.layers.insert(NearSurface, surface);
+        assert!(e.qc_issues().is_empty());
+
+        // pressure alone, without the temperature needed to reduce it
+        let mut low = WxEntryLayer::empty(AGL(10));
+        low.pressure = Some(200.);
+        e.layers.insert(AGL(10), low);
+        let issues = e.qc_issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].layer, Some(AGL(10)));
+        assert_eq!(issues[0].param, Some(Param::Pressure));
+
+        // 200 hPa is fine around 12 km up
+        let mut high = WxEntryLayer::empty(MSL(12000));
+        high.height_msl = Some(12000.);
+        high.pressure = Some(200.);
+        e.layers.insert(MSL(12000), high);
+        assert_eq!(e.qc_issues().len(), 1);
+    }
+
+    #[test]
+    fn test_mean_wind() {
+        let mut e = WxEntry::empty(&test_station());
+        assert!(e.mean_wind(&[NearSurface, AGL(10)]).is_none());
+
+        let mut low = WxEntryLayer::empty(NearSurface);
+        low.wind_direction = Some(Direction::from_degrees(360).unwrap());
+        low.wind_speed = Some(10.);
+        let mut high = WxEntryLayer::empty(AGL(10));
+        high.wind_direction = Some(Direction::from_degrees(180).unwrap());
+        high.wind_speed = Some(4.);
+        e.layers.insert(NearSurface, low);
+        e.layers.insert(AGL(10), high);
+
+        let mean = e.mean_wind(&[NearSurface, AGL(10), AGL(100)]).unwrap();
+        assert_eq!(mean.direction.degrees(), 0);
+        assert!(float_within_one_decimal(mean.speed, 3.));
+
+        let north = e.mean_wind(&[NearSurface]).unwrap();
+        assert!(float_within_one_decimal(north.speed, 10.));
+
+        e.layers.get_mut(&AGL(10)).unwrap().wind_speed = Some(10.);
+        let calm = e.mean_wind(&[NearSurface, AGL(10)]).unwrap();
+        assert_eq!(calm.speed, 0.);
+    }
+
+    #[test]
+    fn test_wind_new() {
+        let wind = Wind::new(12., 234).unwrap();
+        assert_eq!(wind.direction.degrees(), 230);
+        assert_eq!(wind.speed, 12.);
+
+        assert!(Wind::new(12., 400).is_err());
+
+        let calm = Wind::calm();
+        assert_eq!(calm.speed, 0.);
+        assert_eq!(calm.direction.degrees(), 0);
+    }
+
+    #[test]
+    fn test_temp_category() {
+        assert_eq!(TempCategory::from_fahrenheit(10.), TempCategory::Frigid);
+        assert_eq!(TempCategory::from_fahrenheit(95.), TempCategory::Hot);
+        assert_eq!(TempCategory::from_fahrenheit(60.), TempCategory::Mild);
+        assert_eq!(TempCategory::from_fahrenheit(105.).color_hex(), "#c92a2a");
+
+        let mut e = WxEntryLayer::empty(NearSurface);
+        assert_eq!(e.comfort_category(), None);
+        e.temperature = Some(55.);
+        assert_eq!(e.comfort_category(), Some(TempCategory::Cool));
+    }
+
+    #[test]
+    fn test_sea_level_slp() {
+        let mut e = WxEntryLayer::empty(SeaLevel);
+        e.pressure = Some(1012.5);
+        e.temperature = Some(60.);
+        e.height_msl = Some(-100.);
+        assert_eq!(e.slp(43.), Some(1012.5));
+
+        e.pressure = None;
+        assert_eq!(e.slp(43.), None);
+    }
+
+    #[test]
+    fn test_available_fields() {
+        let mut e = WxEntry::empty(&test_station());
+        assert!(e.available_fields().is_empty());
+
+        e.altimeter = Some(1013.);
+        e.skycover = Some(SkyCoverage::Clear);
+        e.precip_1h = Some(Precip { unknown: 0.1, rain: 0., snow: 0. });
+        assert_eq!(e.available_fields(), vec![Param::SkyCover, Param::Altimeter, Param::Precip1h]);
+
+        let mut l = WxEntryLayer::empty(NearSurface);
+        l.temperature = Some(50.);
+        l.wind_speed = Some(3.);
+        assert_eq!(l.available_fields(), vec![Param::Temperature, Param::WindSpeed]);
+        assert!(!l.has(Param::Altimeter));
+    }
+
+    #[test]
+    fn test_cardinal() {
+        assert_eq!(Direction(237).cardinal(), "WSW");
+        assert_eq!(Direction(359).cardinal(), "N");
+        assert_eq!(Direction(725).cardinal(), "N");
+
+        let expected = ["N", "N", "NNE", "NNE", "NE", "NE", "ENE", "ENE", "E", "E",
+                        "E", "ESE", "ESE", "SE", "SE", "SSE", "SSE", "S", "S", "S",
+                        "SSW", "SSW", "SW", "SW", "WSW", "WSW", "W", "W", "W", "WNW",
+                        "WNW", "NW", "NW", "NNW", "NNW", "N"];
+        for (i, e) in expected.iter().enumerate() {
+            assert_eq!(Direction::from_degrees(i as u16 * 10).unwrap().cardinal(), *e);
+        }
+    }
+
+    #[test]
+    fn test_direction_to() {
+        let west = Wind::new(10., 270).unwrap();
+        assert_eq!(west.direction_to().degrees(), 90);
+
+        let north = Wind::new(10., 360).unwrap();
+        assert_eq!(north.direction_to().degrees(), 180);
+        assert_eq!(north.direction_to().opposite().degrees(), 0);
+        // deserialized values skip from_degrees' range check
+        let big: Direction = serde_json::from_str("65500").unwrap();
+        assert_eq!(big.opposite().degrees(), 160); // 65500 wraps to 340
+        assert_eq!(Direction(u16::MAX).opposite().degrees(), 195);
+    }
+
+    #[test]
+    fn test_sanitize_degrees() {
+        assert_eq!(Direction::sanitize_degrees(354).unwrap(), 350);
+        assert_eq!(Direction::sanitize_degrees(355).unwrap(), 0);
+        assert_eq!(Direction::sanitize_degrees(358).unwrap(), 0);
+        assert_eq!(Direction::sanitize_degrees(360).unwrap(), 0);
+        assert_eq!(Direction::sanitize_degrees(0).unwrap(), 0);
+        assert_eq!(Direction::sanitize_degrees(235).unwrap(), 240);
+        assert!(Direction::sanitize_degrees(361).is_err());
+        assert!(Direction::sanitize_degrees(u16::MAX).is_err());
+    }
+
+    #[test]
+    fn test_gust_factor() {
+        let mut wind = Wind::new(15., 230).unwrap();
+        assert_eq!(wind.gust_factor(), None);
+        wind.gust = Some(25.);
+        assert!((wind.gust_factor().unwrap() - 25. / 15.).abs() < 1e-6);
+
+        assert_eq!(Wind::calm().gust_factor(), None);
+
+        let mut e = WxEntryLayer::empty(NearSurface);
+        e.wind_speed = Some(15.);
+        e.wind_gust = Some(25.);
+        assert_eq!(e.gust_factor(), wind.gust_factor());
+
+        e.wind_speed = Some(0.);
+        assert_eq!(e.gust_factor(), None);
+    }
+
+    #[test]
+    fn test_slp_lapse_rate() {
+        let mut e = WxEntryLayer::empty(NearSurface);
+        e.pressure = Some(900.);
+        e.temperature = Some(50.);
+        e.height_msl = Some(1000.);
+
+        let standard = e.slp(43.).unwrap();
+        assert_eq!(e.slp_with(43., SlpOptions::default()), Some(standard));
+
+        let mixed = e.slp_with(43., SlpOptions { lapse_rate: 0.0098, ..Default::default() }).unwrap();
+        let inversion = e.slp_with(43., SlpOptions { lapse_rate: -0.002, ..Default::default() }).unwrap();
+        assert!(mixed < standard, "{mixed} {standard}");
+        assert!(inversion > standard, "{inversion} {standard}");
+    }
+
+    #[test]
+    fn test_advisory_thresholds() {
+        assert_eq!(HeatAdvisory::from_heat_index(104.9), HeatAdvisory::None);
+        assert_eq!(HeatAdvisory::from_heat_index(105.), HeatAdvisory::Advisory);
+        assert_eq!(HeatAdvisory::from_heat_index(109.9), HeatAdvisory::Advisory);
+        assert_eq!(HeatAdvisory::from_heat_index(110.), HeatAdvisory::Warning);
+
+        assert_eq!(ColdAdvisory::from_wind_chill(-14.9), ColdAdvisory::None);
+        assert_eq!(ColdAdvisory::from_wind_chill(-15.), ColdAdvisory::Advisory);
+        assert_eq!(ColdAdvisory::from_wind_chill(-24.9), ColdAdvisory::Advisory);
+        assert_eq!(ColdAdvisory::from_wind_chill(-25.), ColdAdvisory::Warning);
+    }
+
+    #[test]
+    fn test_layer_advisories() {
+        let mut l = WxEntryLayer::empty(NearSurface);
+        assert_eq!(l.heat_advisory(), None);
+        assert_eq!(l.wind_chill_advisory(), None);
+
+        // 100°F with a 75°F dewpoint, heat index around 114°F
+        l.temperature = Some(100.);
+        l.dewpoint = Some(75.);
+        assert_eq!(l.heat_advisory(), Some(HeatAdvisory::Warning));
+        assert_eq!(l.wind_chill_advisory(), Some(ColdAdvisory::None));
+
+        // 95°F with a 70°F dewpoint, heat index around 103°F
+        l.temperature = Some(95.);
+        l.dewpoint = Some(70.);
+        assert_eq!(l.heat_advisory(), Some(HeatAdvisory::None));
+
+        // -5°F in a 20 kt wind, wind chill around -30°F
+        l.temperature = Some(-5.);
+        l.dewpoint = Some(-15.);
+        assert_eq!(l.wind_chill_advisory(), None);
+        l.wind_speed = Some(20.);
+        assert_eq!(l.wind_chill_advisory(), Some(ColdAdvisory::Warning));
+        assert_eq!(l.heat_advisory(), Some(HeatAdvisory::None));
+
+        // 5°F in a 10 kt wind, wind chill around -10°F
+        l.temperature = Some(5.);
+        l.wind_speed = Some(10.);
+        assert_eq!(l.wind_chill_advisory(), Some(ColdAdvisory::None));
+    }
+
+    #[test]
+    fn test_coverage_from_oktas() {
+        use CloudLayerCoverage::*;
+
+        let expected = [None, Some(Few), Some(Few), Some(Scattered), Some(Scattered),
+            Some(Broken), Some(Broken), Some(Broken), Some(Overcast), None];
+        for (oktas, e) in expected.iter().enumerate() {
+            assert_eq!(CloudLayerCoverage::from_oktas(oktas as u8), *e, "{oktas}");
+        }
+
+        // round trips through the upper end of each range
+        for c in [Few, Scattered, Broken, Overcast] {
+            assert_eq!(CloudLayerCoverage::from_oktas(c.oktas()), Some(c));
+        }
+    }
+
+    #[test]
+    fn test_coverage_from_fraction() {
+        use CloudLayerCoverage::*;
+
+        assert_eq!(CloudLayerCoverage::from_fraction(0.), None);
+        assert_eq!(CloudLayerCoverage::from_fraction(0.02), Some(Few));
+        assert_eq!(CloudLayerCoverage::from_fraction(0.25), Some(Few));
+        assert_eq!(CloudLayerCoverage::from_fraction(0.45), Some(Scattered));
+        assert_eq!(CloudLayerCoverage::from_fraction(0.75), Some(Broken));
+        assert_eq!(CloudLayerCoverage::from_fraction(0.98), Some(Broken));
+        assert_eq!(CloudLayerCoverage::from_fraction(1.), Some(Overcast));
+        assert_eq!(CloudLayerCoverage::from_fraction(f32::NAN), None);
+    }
+
+    #[test]
+    fn test_best_slp_prefers_sea_level_layer() {
+        let mut e = entry_at(&test_station(), Default::default(), 70.);
+        let surface = e.layers.get_mut(&NearSurface).unwrap();
+        surface.pressure = Some(1000.);
+        surface.height_msl = Some(100.);
+        let reduced = e.best_slp().unwrap();
+        assert!(reduced > 1000.);
+
+        let mut sea_level = WxEntryLayer::empty(SeaLevel);
+        sea_level.pressure = Some(1012.8);
+        e.layers.insert(SeaLevel, sea_level);
+        assert_eq!(e.best_slp(), Some(1012.8));
+        assert_eq!(e.layers[&SeaLevel].slp(e.latitude()), Some(1012.8));
+    }
+
+    #[test]
+    fn test_wind_display_in() {
+        let mut wind = Wind::new(15.2, 230).unwrap();
+        assert_eq!(wind.display_in(SpeedUnit::Knots, 0), "230° @ 15 kts");
+        assert_eq!(wind.display_in(SpeedUnit::Knots, 2), "230° @ 15.20 kts");
+        assert_eq!(wind.display_in(SpeedUnit::Kph, 1), "230° @ 28.2 km/h");
+
+        wind.gust = Some(25.);
+        assert_eq!(wind.display_in(SpeedUnit::Mph, 0), "230° @ 17 mph G 29 mph");
+
+        // Display is unchanged
+        assert_eq!(wind.to_string(), "230°@15.2 kts");
+    }
+
+    #[test]
+    fn test_wind_to_metar() {
+        let mut wind = Wind::new(15., 230).unwrap();
+        assert_eq!(wind.to_metar(), "23015KT");
+
+        wind.gust = Some(25.);
+        assert_eq!(wind.to_metar(), "23015G25KT");
+
+        assert_eq!(Wind::new(5.4, 3).unwrap().to_metar(), "36005KT");
+        assert_eq!(Wind::new(8., 356).unwrap().to_metar(), "36008KT");
+        assert_eq!(Wind::calm().to_metar(), "00000KT");
+    }
+
+    #[test]
+    fn test_relative_humidity_ice() {
+        // -10°C with a -12°C dewpoint
+        let mut l = WxEntryLayer::empty(NearSurface);
+        l.temperature = Some(14.);
+        l.dewpoint = Some(10.4);
+
+        let water = l.relative_humidity().unwrap();
+        let ice = l.relative_humidity_ice().unwrap();
+        assert!((water - 85.).abs() < 1., "{water}");
+        assert!((ice - 94.2).abs() < 1., "{ice}");
+
+        // saturated with respect to water is supersaturated with respect to ice
+        l.dewpoint = Some(14.);
+        assert!(l.relative_humidity_ice().unwrap() > 100.);
+
+        l.temperature = Some(50.);
+        assert_eq!(l.relative_humidity_ice(), None);
+    }
+
+    #[test]
+    fn test_qfe_from_altimeter() {
+        let mut station = test_station();
+        station.altitude = 1000.;
+
+        // the standard atmosphere is about 898.7 hPa at 1000 m
+        let qfe = station.qfe_from_altimeter(1013.25);
+        assert!((1013.25 - qfe - 114.).abs() < 1., "{qfe}");
+
+        station.altitude = 0.;
+        assert!((station.qfe_from_altimeter(1013.25) - 1013.25).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_precip_convert() {
+        let p = Precip { unknown: 0., rain: 1., snow: 0.5 };
+
+        let mm = p.convert(PrecipUnit::Millimeters);
+        assert_eq!(mm.rain, 25.4);
+        assert_eq!(mm.snow, 12.7);
+        assert_eq!(mm.unknown, 0.);
+
+        assert_eq!(p.convert(PrecipUnit::Inches).rain, 1.);
+        assert_eq!(p.display_in(PrecipUnit::Millimeters), "Rain: 25.40 mm, Snow: 12.70 mm, Unknown: 0.00 mm");
+    }
+
+    #[test]
+    fn test_best_values_from_agl_layers() {
+        let mut e = WxEntry::empty(&test_station());
+        assert_eq!(e.best_temperature(), None);
+        assert!(e.best_wind().is_none());
+        assert_eq!(e.best_pressure(), None);
+
+        let mut low = WxEntryLayer::empty(AGL(2));
+        low.temperature = Some(45.);
+        low.wind_speed = Some(8.);
+        low.wind_direction = Some(Direction::from_degrees(270).unwrap());
+
+        let mut high = WxEntryLayer::empty(AGL(10));
+        high.temperature = Some(40.);
+        high.pressure = Some(1010.);
+
+        e.layers.insert(AGL(10), high);
+        e.layers.insert(AGL(2), low);
+        e.layers.insert(NearSurface, WxEntryLayer::empty(NearSurface));
+
+        assert_eq!(e.best_temperature(), Some(45.));
+        assert_eq!(e.best_wind().map(|w| w.speed), Some(8.));
+        assert_eq!(e.best_pressure(), Some(1010.));
+
+        let mut indoor = WxEntryLayer::empty(Indoor);
+        indoor.temperature = Some(70.);
+        indoor.pressure = Some(1005.);
+        e.layers.insert(Indoor, indoor);
+
+        // indoor is never used for temperature, and outdoor pressure wins
+        assert_eq!(e.best_temperature(), Some(45.));
+        assert_eq!(e.best_pressure(), Some(1010.));
+
+        e.layers.remove(&AGL(10));
+        assert_eq!(e.best_pressure(), Some(1005.));
+    }
+}
@@ -0,0 +1,23 @@
+// fixtures shared by the unit tests
+
+use chrono::{DateTime, Utc};
+
+use crate::{Layer, Station, WxEntry, WxEntryLayer};
+
+pub fn test_station() -> Station {
+    Station {
+        name: "Test".into(),
+        altitude: 28.0,
+        coords: (43.1348, -70.9358),
+    }
+}
+
+// an entry at dt with only a NearSurface temperature (°F)
+pub fn entry_at(station: &Station, dt: DateTime<Utc>, temp: f32) -> WxEntry {
+    let mut entry = WxEntry::empty(station);
+    let mut surface = WxEntryLayer::empty(Layer::NearSurface);
+    surface.temperature = Some(temp);
+    entry.layers.insert(Layer::NearSurface, surface);
+    entry.date_time = dt;
+    entry
+}